@@ -5,49 +5,134 @@ use syntax::tokens::{
 };
 
 use syntax::lexer::Lexer;
+use syntax::bigint::BigInt;
+
+// Row and column, counted from 1, where a node starts in its source
+pub type Position = (u32, u32);
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     If(Box<Expression>, Box<Statement>),
     IfElse(Box<Expression>, Box<Statement>, Box<Statement>),
-    Variable(String, Box<Expression>),
+    Try(Box<Statement>, Option<String>, Box<Statement>, Position),
+    Defer(Box<Statement>),
+    While(Option<String>, Box<Expression>, Box<Statement>),
+    For(Option<String>, String, Box<Expression>, Box<Statement>),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Variable(String, bool, Box<Expression>),
+    Variables(Box<Vec<String>>, bool, Box<Expression>),
+    Const(String, Box<Expression>),
     Block(Box<Vec<Statement>>),
     Expression(Box<Expression>),
     Assignment(String, Box<Expression>),
+    Struct(String, Box<Vec<String>>, Box<Vec<(String, String)>>),
+    Enum(String, Box<Vec<String>>, Box<Vec<(String, Vec<String>)>>),
+    Trait(String, Box<Vec<String>>, Box<Vec<Method>>),
+    Impl(Option<String>, String, Box<Vec<Statement>>),
+    Macro(String, Box<Vec<String>>, Box<Vec<Statement>>, Position),
+    Expansion(String, Position, Position, Box<Vec<Statement>>),
+    Import(Box<Vec<String>>, Option<String>),
+    Public(Box<Statement>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     Integer(i64),
+    BigInteger(BigInt),
     Float(f64),
     Text(String),
+    Char(char),
     Bool(bool),
-    Call(Box<Expression>, Box<Vec<Expression>>),
+    Call(Box<Expression>, Box<Vec<Expression>>, Box<Vec<(String, Expression)>>, Position),
     Assignment(Box<Expression>, Box<Expression>),
+    CompoundAssignment(Box<Expression>, BinOp, Box<Expression>),
     Dot(Box<Expression>, Box<Expression>),
-    Index(String, Box<Expression>),
+    Index(Box<Expression>, Box<Expression>),
     Array(Box<Vec<Expression>>),
+    Tuple(Box<Vec<Expression>>),
+    Map(Box<Vec<(Expression, Expression)>>),
+    Set(Box<Vec<Expression>>),
+    Record(String, Box<Vec<(String, Expression)>>),
+    Range(Box<Expression>, Box<Expression>, bool),
+    Identifier(String),
+    Typed(Box<Expression>, String),
+    Operation(Box<Expression>, BinOp, Box<Expression>, Position),
+    Definition(Option<String>, Box<Vec<String>>, Box<Vec<Parameter>>, Box<Vec<Statement>>, Option<String>, bool),
+    Lambda(Box<Vec<Parameter>>, Box<Statement>, Option<String>, bool),
+    Return(Option<Box<Expression>>),
+    Throw(Box<Expression>, Position),
+    Yield(Box<Expression>),
+    Match(Box<Expression>, Box<Vec<(Pattern, Option<Expression>, Expression)>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name:     String,
+    pub kind:     Option<String>,
+    pub default:  Option<Expression>,
+    pub variadic: bool,
+}
+
+impl Parameter {
+    pub fn new(name: String, kind: Option<String>) -> Parameter {
+        Parameter {
+            name:     name,
+            kind:     kind,
+            default:  None,
+            variadic: false,
+        }
+    }
+}
+
+// A method declared by a trait, `body` being its default if it has one
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub name:        String,
+    pub type_params: Vec<String>,
+    pub params:      Vec<Parameter>,
+    pub kind:        Option<String>,
+    pub body:        Option<Vec<Statement>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Char(char),
+    Bool(bool),
     Identifier(String),
-    Operation(Box<Expression>, BinOp, Box<Expression>),
-    Definition(Option<String>, Box<Vec<String>>, Box<Vec<Statement>>, Option<String>),
-    Lambda(Box<Vec<String>>, Box<Statement>, Option<String>),
-    Return(Box<Expression>),
+    Array(Box<Vec<Pattern>>),
+    Tuple(Box<Vec<Pattern>>),
+    Record(String, Box<Vec<(String, Pattern)>>),
+    Variant(String, Box<Vec<Pattern>>),
 }
 
+// Name, type parameters, parameters and return type of a definition
+type Signature = (Option<String>, Vec<String>, Vec<Parameter>, Option<String>);
+
 pub struct Parser {
-    lexer: Lexer,
+    lexer:     Lexer,
+    bare_args: bool,
+    yields:    bool,
 }
 
 impl Parser {
     pub fn new() -> Parser {
         Parser {
-            lexer: Lexer::new(),
+            lexer:     Lexer::new(),
+            bare_args: false,
+            yields:    false,
         }
     }
 
     pub fn from(lexer: Lexer) -> Parser {
         Parser {
-            lexer: lexer,
+            lexer:     lexer,
+            bare_args: false,
+            yields:    false,
         }
     }
 
@@ -57,89 +142,110 @@ impl Parser {
         parser.parse_full()
     }
 
+    // Invoked on the operator following `expr`, leaving the last operand current
     fn parse_bin_op(&mut self, expr: Expression) -> Result<Expression, String> {
         let mut expr_list = vec!(expr);
-        let mut oper_list: Vec<(BinOp, u8)> = Vec::new();
+        let mut oper_list: Vec<(BinOp, u8, Position)> = Vec::new();
 
-        oper_list.push(Lexer::bin_op(
-            &self.lexer.current_token_content()[..]
-        ).unwrap());
+        loop {
+            let (op, prec) = Lexer::bin_op(&self.lexer.current_token_content()[..]).unwrap();
+            let position   = self.lexer.current_token().position();
 
-        self.lexer.next_token();
+            // fold whatever binds at least as tight, so equal precedence is left associative
+            while let Some(&(_, last, _)) = oper_list.last() {
+                if last > prec {
+                    break
+                }
+
+                let right = expr_list.pop().unwrap();
+                let left  = expr_list.pop().unwrap();
 
-        expr_list.push(try!(self.parse_word()));
+                let (op, _, position) = oper_list.pop().unwrap();
 
-        let mut done = false;
+                expr_list.push(Parser::operation(left, op, right, position));
+            }
+
+            oper_list.push((op, prec, position));
 
-        while expr_list.len() > 1 {
+            self.lexer.next_token();
 
-            if !done && self.lexer.next_token() {
-                if self.lexer.current_token().token_type != TokenType::BinOp {
-                    self.lexer.previous_token();
-                    done = true;
+            expr_list.push(try!(self.parse_word()));
 
+            if self.lexer.next_token() {
+                if self.lexer.tokens_remaining() > 0 && self.lexer.current_token().token_type == TokenType::BinOp {
                     continue
                 }
 
-                let (op, prec) = Lexer::bin_op(&self.lexer.current_token_content()[..]).unwrap();
+                self.lexer.previous_token();
+            }
+
+            break
+        }
+
+        while let Some((op, _, position)) = oper_list.pop() {
+            let right = expr_list.pop().unwrap();
+            let left  = expr_list.pop().unwrap();
 
-                if prec > oper_list.last().unwrap().1 {
+            expr_list.push(Parser::operation(left, op, right, position));
+        }
 
-                    let left = expr_list.pop().unwrap();
-                    let right = expr_list.pop().unwrap();
+        Ok(expr_list.pop().unwrap())
+    }
 
-                    expr_list.push(Expression::Operation(
-                        Box::new(left),
-                        oper_list.pop().unwrap().0,
-                        Box::new(right),
-                    ));
+    // Desugars pipelines and composition into calls, made at the operator's `position`
+    fn operation(left: Expression, op: BinOp, right: Expression, position: Position) -> Expression {
+        match op {
+            // `x |> f(a)` is `f(x, a)`
+            BinOp::Pipe => match right {
+                Expression::Call(callee, args, named, position) => {
+                    let mut args = *args;
 
-                    self.lexer.next_token();
+                    args.insert(0, left);
 
-                    expr_list.push(try!(self.parse_word()));
-                    oper_list.push((op, prec));
+                    Expression::Call(callee, Box::new(args), named, position)
+                },
 
-                    continue
-                }
-                self.lexer.next_token();
+                _ => Expression::Call(Box::new(right), Box::new(vec!(left)), Box::default(), position),
+            },
 
-                expr_list.push(try!(self.parse_word()));
-                oper_list.push((op, prec));
-            }
+            // `f >> g` is `lambda x: g(f(x))`, named so no source identifier can shadow it
+            BinOp::Compose => {
+                let x = String::from("@x");
 
-            let left = expr_list.pop().unwrap();
-            let right = expr_list.pop().unwrap();
+                let inner = Expression::Call(
+                    Box::new(left), Box::new(vec!(Expression::Identifier(x.clone()))), Box::default(), position
+                );
 
-            let oper = Expression::Operation(
-                Box::new(left),
-                oper_list.pop().unwrap().0,
-                Box::new(right),
-            );
+                let outer = Expression::Call(Box::new(right), Box::new(vec!(inner)), Box::default(), position);
 
-            expr_list.push(oper);
-        }
+                Expression::Lambda(
+                    Box::new(vec!(Parameter::new(x, None))),
+                    Box::new(Statement::Expression(Box::new(outer))),
+                    None,
+                    false,
+                )
+            },
 
-        Ok(expr_list.pop().unwrap())
+            _ => Expression::Operation(Box::new(left), op, Box::new(right), position),
+        }
     }
 
     fn parse_word(&mut self) -> Result<Expression, String> {
         match self.lexer.current_token().token_type {
-            TokenType::Integer => {
-                Ok(Expression::Integer(
-                    self.lexer.current_token_content().parse::<i64>().unwrap()
-                ))
+            TokenType::Integer | TokenType::Float => {
+                Parser::parse_number(&self.lexer.current_token_content())
             },
 
-            TokenType::Float => {
-                Ok(Expression::Float(
-                    self.lexer.current_token_content().parse::<f64>().unwrap()
-                ))
+            TokenType::Text => {
+                let text = Expression::Text(self.lexer.current_token_content());
+
+                self.parse_postfix(text)
             },
 
-            TokenType::Text => {
-                Ok(Expression::Text(
-                    self.lexer.current_token_content()
-                ))
+            TokenType::Char => {
+                let c = Expression::Char(self.lexer.current_token_content().chars().next().unwrap());
+
+                self.parse_postfix(c)
             },
 
             TokenType::True => {
@@ -150,157 +256,196 @@ impl Parser {
                 Ok(Expression::Bool(false))
             },
 
+            // grouping, or a tuple if there's a comma, e.g. `(a, b)`, `(a,)` and `()`
             TokenType::LParen => {
                 self.lexer.next_token();
 
-                let expr = try!(self.parse_expression());
+                let mut stack = Vec::new();
+                let mut tuple = false;
+
+                while try!(self.in_list(TokenType::RParen, "tuple")) {
+                    stack.push(try!(self.parse_nested()));
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        tuple = true;
+
+                        self.lexer.next_token();
+                    } else {
+                        try!(self.lexer.match_current_token(TokenType::RParen));
+                    }
+                }
+
+                let expr = match stack.len() {
+                    1 if !tuple => stack.remove(0),
+                    _           => Expression::Tuple(Box::new(stack)),
+                };
 
-                try!(self.lexer.match_current_token(TokenType::RParen));
+                self.parse_postfix(expr)
+            },
 
+            TokenType::LBracket => {
                 self.lexer.next_token();
 
-                if self.lexer.current_token().token_type == TokenType::LParen {
-                    return self.parse_caller(expr)
+                let mut stack = Vec::new();
+
+                while try!(self.in_list(TokenType::RBracket, "array")) {
+                    stack.push(try!(self.parse_nested()));
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        self.lexer.next_token();
+                    }
                 }
 
-                self.lexer.previous_token();
+                self.parse_postfix(Expression::Array(Box::new(stack)))
+            },
+
+            TokenType::LBrace => {
+                let collection = try!(self.parse_collection());
 
-                Ok(expr)
+                self.parse_postfix(collection)
             },
 
             TokenType::Identifier => {
-                let id = Expression::Identifier(self.lexer.current_token_content());
+                let name = self.lexer.current_token_content();
 
-                if self.lexer.next_token() {
-                    return match self.lexer.current_token().token_type {
-                        TokenType::BinOp => {
-                            self.parse_bin_op(id)
-                        },
-                        
-                        TokenType::LParen => {
-                            self.parse_caller(id)
-                        },
-                        
-                        _ => {
-                            self.lexer.previous_token();
-                            Ok(id)
-                        }
-                    }
+                if self.next_on_row(TokenType::LBrace) {
+                    let record = try!(self.parse_record(name));
+
+                    return self.parse_postfix(record)
                 }
 
-                Ok(id)
+                self.parse_postfix(Expression::Identifier(name))
             },
             
             TokenType::Lambda => {
                 self.lexer.next_token();
 
-                let mut arg_stack = Vec::new();
+                let params: Vec<Parameter>;
 
-                while self.lexer.current_token().token_type == TokenType::Identifier {
-                    arg_stack.push(self.lexer.current_token_content());
-                    
-                    self.lexer.next_token();
+                if self.lexer.current_token().token_type == TokenType::LParen {
+                    params = try!(self.parse_params());
 
-                    if self.lexer.current_token().token_type == TokenType::Comma {
-                        self.lexer.next_token();
-                    }
-                }
+                    self.lexer.next_token();
+                } else {
+                    let mut stack = Vec::new();
 
-                let ret_type: Option<String>;
+                    while self.lexer.tokens_remaining() > 0 && self.lexer.current_token().token_type == TokenType::Identifier {
+                        stack.push(Parameter::new(self.lexer.current_token_content(), None));
 
-                if self.lexer.current_token().token_type == TokenType::Arrow {
-                    self.lexer.next_token();
+                        self.lexer.next_token();
 
-                    if self.lexer.current_token().token_type == TokenType::Identifier {
-                        ret_type = Some(self.lexer.current_token_content());
-                    } else {
-                        ret_type = None;
+                        if self.lexer.current_token().token_type == TokenType::Comma {
+                            self.lexer.next_token();
+                        }
                     }
 
-                } else {
-                    ret_type = None;
+                    params = stack;
                 }
 
-                self.lexer.next_token();
+                let ret_type = try!(self.parse_return_type());
+                let outer    = ::std::mem::replace(&mut self.yields, false);
 
-                try!(self.lexer.match_current_token(TokenType::Colon));
+                let body = if self.lexer.current_token().token_type == TokenType::Do {
+                    Statement::Block(Box::new(try!(self.parse_block())))
+                } else {
+                    try!(self.lexer.match_current_token(TokenType::Colon));
 
-                self.lexer.next_token();
+                    self.lexer.next_token();
 
-                let block_stmt = try!(self.parse_statement());
+                    try!(self.parse_statement())
+                };
+
+                let generator = ::std::mem::replace(&mut self.yields, outer);
 
                 Ok(Expression::Lambda(
-                    Box::new(arg_stack),
-                    Box::new(block_stmt),
+                    Box::new(params),
+                    Box::new(body),
                     ret_type,
+                    generator,
                 ))
             },
 
             TokenType::Definition => {
                 self.lexer.next_token();
 
-                let name: Option<String>;
+                let (name, type_params, params, ret_type) = try!(self.parse_signature());
 
-                if self.lexer.current_token().token_type == TokenType::Identifier {
-                    name = Some(self.lexer.current_token_content());
-                    self.lexer.next_token();
-                } else {
-                    name = None;
-                }
+                let outer      = ::std::mem::replace(&mut self.yields, false);
+                let block_body = try!(self.parse_block());
+                let generator  = ::std::mem::replace(&mut self.yields, outer);
 
-                try!(self.lexer.match_current_token(TokenType::LParen));
+                Ok(Expression::Definition(
+                    name,
+                    Box::new(type_params),
+                    Box::new(params),
+                    Box::new(block_body),
+                    ret_type,
+                    generator,
+                ))
+            },
 
-                self.lexer.next_token();
+            // `return` alone returns unit, and several values are returned as a tuple, e.g. `return q, r`
+            TokenType::Return => {
+                if !self.next_on_row_value() {
+                    return Ok(Expression::Return(None))
+                }
 
-                let mut arg_stack = Vec::new();
+                let mut stack = vec!(try!(self.parse_expression()));
 
-                while self.lexer.current_token().token_type == TokenType::Identifier {
-                    arg_stack.push(self.lexer.current_token_content());
-                    
+                while self.next_on_row(TokenType::Comma) {
                     self.lexer.next_token();
 
-                    if self.lexer.current_token().token_type == TokenType::Comma {
-                        self.lexer.next_token();
-                    }
+                    stack.push(try!(self.parse_expression()));
                 }
 
-                try!(self.lexer.match_current_token(TokenType::RParen));
+                let expr = match stack.len() {
+                    1 => stack.remove(0),
+                    _ => Expression::Tuple(Box::new(stack)),
+                };
 
-                self.lexer.next_token();
+                Ok(Expression::Return(Some(Box::new(expr))))
+            },
 
-                let ret_type: Option<String>;
+            TokenType::Throw => {
+                let position = self.lexer.current_token().position();
 
-                if self.lexer.current_token().token_type == TokenType::Arrow {
-                    self.lexer.next_token();
+                try!(self.next_value("throw"));
 
-                    if self.lexer.current_token().token_type == TokenType::Identifier {
-                        ret_type = Some(self.lexer.current_token_content());
-                    } else {
-                        ret_type = None;
-                    }
+                let expr = try!(self.parse_expression());
 
-                } else {
-                    ret_type = None;
-                }
+                Ok(Expression::Throw(Box::new(expr), position))
+            },
 
-                self.lexer.next_token();
+            // the function around a `yield` is a generator
+            TokenType::Yield => {
+                self.yields = true;
 
-                let block_body = try!(self.parse_block());
+                try!(self.next_value("yield"));
 
-                Ok(Expression::Definition(
-                    name,
-                    Box::new(arg_stack),
-                    Box::new(block_body),
-                    ret_type,
-                ))
+                let expr = try!(self.parse_expression());
+
+                Ok(Expression::Yield(Box::new(expr)))
             },
 
-            TokenType::Return => {
+            TokenType::Match => {
                 self.lexer.next_token();
 
-                let expr = try!(self.parse_expression());
+                let subject = try!(self.parse_expression());
+
+                self.lexer.next_token();
 
-                Ok(Expression::Return(Box::new(expr)))
+                let arm_tokens = try!(self.parse_block_tokens());
+
+                let mut parser = Parser::from(Lexer::from(arm_tokens));
+                let arms       = try!(parser.parse_arms());
+
+                self.yields |= parser.yields;
+
+                Ok(Expression::Match(Box::new(subject), Box::new(arms)))
             },
 
             _ => {
@@ -316,19 +461,17 @@ impl Parser {
 
                 self.lexer.next_token();
 
-                if self.lexer.current_token().token_type != TokenType::Assign {
-                    self.lexer.previous_token();
-
-                    let expr = try!(self.parse_expression());
+                if self.lexer.current_token().token_type == TokenType::Colon {
+                    self.lexer.next_token();
 
-                    return Ok(Statement::Expression(Box::new(expr)))
+                    return self.parse_loop(Some(id))
                 }
 
-                self.lexer.next_token();
+                self.lexer.previous_token();
 
                 let expr = try!(self.parse_expression());
 
-                Ok(Statement::Assignment(id, Box::new(expr)))
+                self.parse_assignment(expr)
             },
 
             TokenType::If => {
@@ -354,92 +497,1407 @@ impl Parser {
                     ))
                 }
 
+                self.lexer.previous_token();
+
                 Ok(Statement::If(
                     Box::new(condition),
                     Box::new(Statement::Block(Box::new(body))),
                 ))
             },
 
-            _ => {
-                let expr = try!(self.parse_expression());
-                Ok(Statement::Expression(Box::new(expr)))
-            }
-        }
-    }
+            // `try do ... end catch e do ... end`, the error being bound to `e` if named
+            TokenType::Try => {
+                let position = self.lexer.current_token().position();
 
-    pub fn parse_full(&mut self) -> Result<Vec<Statement>, String> {
-        let mut statement_stack = Vec::new();
+                self.lexer.next_token();
 
-        loop {
-            if self.lexer.tokens_remaining() < 1 {
-                break
-            }
+                let body = try!(self.parse_block());
 
-            statement_stack.push(try!(self.parse_statement()));
+                self.lexer.next_token();
 
-            self.lexer.next_token();
-        }
+                try!(self.lexer.match_current_token(TokenType::Catch));
 
-        Ok(statement_stack)
-    }
+                let name = match self.next_on_row(TokenType::Identifier) {
+                    true  => Some(self.lexer.current_token_content()),
+                    false => None,
+                };
 
-    fn parse_block(&mut self) -> Result<Vec<Statement>, String> {
-        try!(self.lexer.match_current_token(TokenType::Do));
+                self.lexer.next_token();
 
-        let mut block_tokens = Vec::new();
-        let mut opened_dos   = 1;
+                let handler = try!(self.parse_block());
 
-        while self.lexer.next_token() {
-            if self.lexer.current_token().token_type == TokenType::Do {
-                opened_dos += 1;     
-            } else if self.lexer.current_token().token_type == TokenType::End {
-                opened_dos -= 1;     
-            }
+                Ok(Statement::Try(
+                    Box::new(Statement::Block(Box::new(body))),
+                    name,
+                    Box::new(Statement::Block(Box::new(handler))),
+                    position,
+                ))
+            },
 
-            if opened_dos == 0 {
-                break
-            }
+            // `defer close(f)` or `defer do ... end`, run when the enclosing block exits
+            TokenType::Defer => {
+                self.lexer.next_token();
 
-            block_tokens.push(self.lexer.current_token().clone());
-        }
+                let body = match self.lexer.current_token().token_type {
+                    TokenType::Do => Statement::Block(Box::new(try!(self.parse_block()))),
+                    _             => Statement::Expression(Box::new(try!(self.parse_expression()))),
+                };
 
-        try!(self.lexer.match_current_token(TokenType::End));
+                Ok(Statement::Defer(Box::new(body)))
+            },
 
-        Parser::parse_from_tokens(block_tokens)
-    }
+            TokenType::Struct => {
+                self.lexer.next_token();
 
-    fn parse_expression(&mut self) -> Result<Expression, String> {
-        let expr = try!(self.parse_word());
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
 
-        self.lexer.next_token();
+                self.lexer.next_token();
 
-        if self.lexer.tokens_remaining() > 0 {
-            if self.lexer.current_token().token_type == TokenType::BinOp {
-                return self.parse_bin_op(expr);
-            }
+                let type_params = try!(self.parse_type_params());
 
-            self.lexer.previous_token();
-        }
+                let field_tokens = try!(self.parse_block_tokens());
+                let fields       = try!(Parser::from(Lexer::from(field_tokens)).parse_fields());
 
-        Ok(expr)
-    }
+                Ok(Statement::Struct(name, Box::new(type_params), Box::new(fields)))
+            },
 
-    // Invoked when LParen is popped
-    fn parse_caller(&mut self, callee: Expression) -> Result<Expression, String> {
-        let mut stack = Vec::new();
+            TokenType::Enum => {
+                self.lexer.next_token();
 
-        self.lexer.next_token();
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
 
-        while self.lexer.current_token().token_type != TokenType::RParen {
-            stack.push(try!(self.parse_expression()));
-            
-            self.lexer.next_token();
+                self.lexer.next_token();
 
-            if self.lexer.current_token().token_type == TokenType::Comma {
+                let type_params = try!(self.parse_type_params());
+
+                let variant_tokens = try!(self.parse_block_tokens());
+                let variants       = try!(Parser::from(Lexer::from(variant_tokens)).parse_variants());
+
+                Ok(Statement::Enum(name, Box::new(type_params), Box::new(variants)))
+            },
+
+            TokenType::Trait => {
                 self.lexer.next_token();
-            }
-        }
 
-        Ok(Expression::Call(Box::new(callee), Box::new(stack)))
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+                self.lexer.next_token();
+
+                let type_params = try!(self.parse_type_params());
+
+                let method_tokens = try!(self.parse_block_tokens());
+                let methods       = try!(Parser::from(Lexer::from(method_tokens)).parse_methods());
+
+                Ok(Statement::Trait(name, Box::new(type_params), Box::new(methods)))
+            },
+
+            // `impl Show for Point do ... end`, or `impl Point do ... end` without a trait
+            TokenType::Impl => {
+                self.lexer.next_token();
+
+                let mut kind = try!(self.parse_type());
+                let mut name = None;
+
+                if self.next_on_row(TokenType::For) {
+                    self.lexer.next_token();
+
+                    name = Some(kind);
+                    kind = try!(self.parse_type());
+                }
+
+                self.lexer.next_token();
+
+                let body = try!(self.parse_block());
+
+                for s in &body {
+                    match *s {
+                        Statement::Expression(ref expr) => match **expr {
+                            Expression::Definition(Some(_), ..) => (),
+                            _ => return Err(format!("expected method in impl but found {:?}", expr)),
+                        },
+                        _ => return Err(format!("expected method in impl but found {:?}", s)),
+                    }
+                }
+
+                Ok(Statement::Impl(name, kind, Box::new(body)))
+            },
+
+            // `const SIZE = 64`, or a function usable in constants, e.g. `const define square(x) do ... end`
+            TokenType::Const => {
+                self.lexer.next_token();
+
+                if self.lexer.current_token().token_type == TokenType::Definition {
+                    let definition = try!(self.parse_word());
+
+                    let name = match definition {
+                        Expression::Definition(Some(ref name), ..) => name.clone(),
+                        _ => return Err(String::from("const function without a name")),
+                    };
+
+                    return Ok(Statement::Const(name, Box::new(definition)))
+                }
+
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+                self.lexer.next_token();
+
+                try!(self.lexer.match_current_token(TokenType::Assign));
+
+                self.lexer.next_token();
+
+                let expr = try!(self.parse_expression());
+
+                Ok(Statement::Const(name, Box::new(expr)))
+            },
+
+            // `macro unless(cond, body) do ... end`, expanded before resolution
+            TokenType::Macro => {
+                let position = self.lexer.current_token().position();
+
+                self.lexer.next_token();
+
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+                self.lexer.next_token();
+
+                try!(self.lexer.match_current_token(TokenType::LParen));
+
+                self.lexer.next_token();
+
+                let mut params = Vec::new();
+
+                while try!(self.in_list(TokenType::RParen, "macro parameters")) {
+                    params.push(try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone());
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        self.lexer.next_token();
+                    }
+                }
+
+                self.lexer.next_token();
+
+                let body = try!(self.parse_block());
+
+                Ok(Statement::Macro(name, Box::new(params), Box::new(body), position))
+            },
+
+            TokenType::Let => {
+                self.lexer.next_token();
+
+                let mutable = self.lexer.current_token().token_type == TokenType::Mut;
+
+                if mutable {
+                    self.lexer.next_token();
+                }
+
+                let mut names = vec!(try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone());
+
+                self.lexer.next_token();
+
+                // `let q, r = divmod(9, 4)` binds the values of a tuple one by one
+                while self.lexer.current_token().token_type == TokenType::Comma {
+                    self.lexer.next_token();
+
+                    names.push(try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone());
+
+                    self.lexer.next_token();
+                }
+
+                try!(self.lexer.match_current_token(TokenType::Assign));
+
+                self.lexer.next_token();
+
+                let mut stack = vec!(try!(self.parse_expression()));
+
+                while self.next_on_row(TokenType::Comma) {
+                    self.lexer.next_token();
+
+                    stack.push(try!(self.parse_expression()));
+                }
+
+                let expr = match stack.len() {
+                    1 => stack.remove(0),
+                    _ => Expression::Tuple(Box::new(stack)),
+                };
+
+                match names.len() {
+                    1 => Ok(Statement::Variable(names.remove(0), mutable, Box::new(expr))),
+                    _ => Ok(Statement::Variables(Box::new(names), mutable, Box::new(expr))),
+                }
+            },
+
+            TokenType::Import => {
+                self.lexer.next_token();
+
+                let mut path = vec!(
+                    try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone()
+                );
+
+                while self.next_on_row(TokenType::Period) {
+                    self.lexer.next_token();
+
+                    path.push(try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone());
+                }
+
+                let alias = if self.next_on_row(TokenType::As) {
+                    self.lexer.next_token();
+
+                    Some(try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone())
+                } else {
+                    None
+                };
+
+                Ok(Statement::Import(Box::new(path), alias))
+            },
+
+            TokenType::Pub => {
+                self.lexer.next_token();
+
+                let statement = try!(self.parse_statement());
+
+                match statement {
+                    Statement::Struct(..)   |
+                    Statement::Enum(..)     |
+                    Statement::Trait(..)    |
+                    Statement::Const(..)    |
+                    Statement::Variable(..) => (),
+
+                    Statement::Expression(ref expr) => match **expr {
+                        Expression::Definition(Some(_), ..) => (),
+                        _ => return Err(format!("can't export {:?}", expr)),
+                    },
+
+                    _ => return Err(format!("can't export {:?}", statement)),
+                }
+
+                Ok(Statement::Public(Box::new(statement)))
+            },
+
+            TokenType::While | TokenType::For => {
+                self.parse_loop(None)
+            },
+
+            TokenType::Break => {
+                Ok(Statement::Break(self.parse_label()))
+            },
+
+            TokenType::Continue => {
+                Ok(Statement::Continue(self.parse_label()))
+            },
+
+            _ => {
+                let expr = try!(self.parse_expression());
+                self.parse_assignment(expr)
+            }
+        }
+    }
+
+    // Assignment to `target` if an assignment operator follows it, e.g. `xs[i] += 1` or `a, b = b, a`
+    fn parse_assignment(&mut self, target: Expression) -> Result<Statement, String> {
+        let start = self.lexer.get_top();
+
+        let mut targets = vec!(target);
+
+        // not knowing yet whether `a, b` is a destructuring, give up quietly if it isn't
+        while self.lexer.next_token() && self.lexer.tokens_remaining() > 0
+              && self.lexer.current_token().token_type == TokenType::Comma {
+            self.lexer.next_token();
+
+            match self.parse_expression() {
+                Ok(e)  => targets.push(e),
+                Err(_) => break,
+            }
+        }
+
+        let remaining = self.lexer.tokens_remaining() > 0;
+
+        let is_assign   = remaining && self.lexer.current_token().token_type == TokenType::Assign;
+        let is_compound = remaining && self.lexer.current_token().token_type == TokenType::CompoundAssign
+                                    && targets.len() == 1;
+
+        if !is_assign && !is_compound {
+            self.lexer.set_top(start);
+
+            return Ok(Statement::Expression(Box::new(targets.remove(0))))
+        }
+
+        let op = match is_compound {
+            true  => Some(Lexer::bin_op(&self.lexer.current_token_content()[.. 1]).unwrap().0),
+            false => None,
+        };
+
+        let target = match targets.len() {
+            1 => targets.remove(0),
+            _ => Expression::Tuple(Box::new(targets)),
+        };
+
+        self.lexer.next_token();
+
+        let mut values = vec!(try!(self.parse_expression()));
+
+        while self.lexer.next_token() {
+            if self.lexer.tokens_remaining() == 0 || self.lexer.current_token().token_type != TokenType::Comma {
+                self.lexer.previous_token();
+                break
+            }
+
+            self.lexer.next_token();
+
+            values.push(try!(self.parse_expression()));
+        }
+
+        let value = match values.len() {
+            1 => values.remove(0),
+            _ => Expression::Tuple(Box::new(values)),
+        };
+
+        match op {
+            Some(op) => {
+                match target {
+                    Expression::Identifier(_) | Expression::Dot(..) | Expression::Index(..) => (),
+                    _ => return Err(format!("can't assign to {:?}", target)),
+                }
+
+                Ok(Statement::Expression(Box::new(
+                    Expression::CompoundAssignment(Box::new(target), op, Box::new(value))
+                )))
+            },
+
+            None => match target {
+                Expression::Identifier(name) => Ok(Statement::Assignment(name, Box::new(value))),
+
+                _ if !Parser::is_target(&target) => Err(format!("can't assign to {:?}", target)),
+
+                _ => Ok(Statement::Expression(Box::new(
+                    Expression::Assignment(Box::new(target), Box::new(value))
+                ))),
+            },
+        }
+    }
+
+    fn is_target(expr: &Expression) -> bool {
+        match *expr {
+            Expression::Identifier(_) |
+            Expression::Dot(..)       |
+            Expression::Index(..)     => true,
+
+            Expression::Array(ref content) |
+            Expression::Tuple(ref content) => content.iter().all(Parser::is_target),
+
+            _ => false,
+        }
+    }
+
+    // Numeric literal such as `0xff`, `1_000`, `2.5e3` or `10i32`, the suffix giving it a type
+    fn parse_number(content: &str) -> Result<Expression, String> {
+        let literal = content.replace('_', "");
+
+        let (negative, literal) = match literal.starts_with('-') {
+            true  => (true, &literal[1 ..]),
+            false => (false, &literal[..]),
+        };
+
+        let (radix, literal) = match literal.get(.. 2) {
+            Some("0x") => (16, &literal[2 ..]),
+            Some("0o") => (8, &literal[2 ..]),
+            Some("0b") => (2, &literal[2 ..]),
+            _          => (10, literal),
+        };
+
+        // separators go between two digits, so `1_000` but neither `1__0` nor `1_000_`
+        let chars: Vec<char> = content.chars().collect();
+
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_' && !(i > 0 && chars[i - 1].is_digit(radix) && i + 1 < chars.len() && chars[i + 1].is_digit(radix)) {
+                return Err(format!("misplaced `_` in number literal: {}", content))
+            }
+        }
+
+        // only decimals can be floats, `f32` and `f64` being hex digits besides
+        let suffixes: &[&str] = match radix {
+            10 => &["i32", "i64", "u8", "f32", "f64"],
+            _  => &["i32", "i64", "u8"],
+        };
+
+        let suffix = suffixes.iter().find(|s| literal.ends_with(*s));
+
+        let digits = match suffix {
+            Some(s) => &literal[.. literal.len() - s.len()],
+            None    => literal,
+        };
+
+        let float = match suffix {
+            Some(s) => s.starts_with('f'),
+            None    => radix == 10 && digits.contains(['.', 'e', 'E']),
+        };
+
+        let number = if float {
+            let value = match digits.parse::<f64>() {
+                Ok(v)  => if negative { -v } else { v },
+                Err(_) => return Err(format!("invalid number literal: {}", content)),
+            };
+
+            let max = match suffix {
+                Some(&"f32") => f64::from(f32::MAX),
+                _            => f64::MAX,
+            };
+
+            if value.abs() > max {
+                return Err(format!("number literal out of range: {}", content))
+            }
+
+            Expression::Float(value)
+        } else {
+            let value = match i128::from_str_radix(digits, radix) {
+                Ok(v)  => Some(if negative { -v } else { v }),
+                Err(e) => match *e.kind() {
+                    ::std::num::IntErrorKind::PosOverflow => None,
+                    _ => return Err(format!("invalid number literal: {}", content)),
+                },
+            };
+
+            let (min, max) = match suffix {
+                Some(&"i32") => (i128::from(i32::MIN), i128::from(i32::MAX)),
+                Some(&"u8")  => (i128::from(u8::MIN), i128::from(u8::MAX)),
+                _            => (i128::from(i64::MIN), i128::from(i64::MAX)),
+            };
+
+            match value {
+                Some(v) if v >= min && v <= max => Expression::Integer(v as i64),
+
+                // without a suffix, anything past an i64 is a big integer
+                _ if suffix.is_none() => match BigInt::from_str_radix(digits, radix, negative) {
+                    Some(v) => Expression::BigInteger(v),
+                    None    => return Err(format!("invalid number literal: {}", content)),
+                },
+
+                _ => return Err(format!("number literal out of range: {}", content)),
+            }
+        };
+
+        match suffix {
+            Some(s) => Ok(Expression::Typed(Box::new(number), String::from(*s))),
+            None    => Ok(number),
+        }
+    }
+
+    // Invoked after `define`, leaving the token after the return type current
+    fn parse_signature(&mut self) -> Result<Signature, String> {
+        let name = match self.lexer.current_token().token_type {
+            TokenType::Identifier => {
+                let name = self.lexer.current_token_content();
+
+                self.lexer.next_token();
+
+                Some(name)
+            },
+
+            _ => None,
+        };
+
+        let type_params = try!(self.parse_type_params());
+
+        try!(self.lexer.match_current_token(TokenType::LParen));
+
+        let params = try!(self.parse_params());
+
+        self.lexer.next_token();
+
+        let ret_type = try!(self.parse_return_type());
+
+        Ok((name, type_params, params, ret_type))
+    }
+
+    // Trait methods, e.g. `define show(self) -> text`, optionally followed by a default body
+    fn parse_methods(&mut self) -> Result<Vec<Method>, String> {
+        let mut methods = Vec::new();
+
+        while self.lexer.tokens_remaining() > 0 {
+            try!(self.lexer.match_current_token(TokenType::Definition));
+
+            self.lexer.next_token();
+
+            let (name, type_params, params, kind) = try!(self.parse_signature());
+
+            let name = match name {
+                Some(n) => n,
+                None    => return Err(format!("expected method name but found {:?}", self.lexer.current_token())),
+            };
+
+            let mut body = None;
+
+            if self.lexer.tokens_remaining() > 0 && self.lexer.current_token().token_type == TokenType::Do {
+                body = Some(try!(self.parse_block()));
+
+                self.lexer.next_token();
+            }
+
+            methods.push(Method {
+                name:        name,
+                type_params: type_params,
+                params:      params,
+                kind:        kind,
+                body:        body,
+            });
+        }
+
+        Ok(methods)
+    }
+
+    // Struct fields, e.g. `x -> int, y -> int`
+    fn parse_fields(&mut self) -> Result<Vec<(String, String)>, String> {
+        let mut fields = Vec::new();
+
+        while self.lexer.tokens_remaining() > 0 {
+            let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            self.lexer.next_token();
+
+            try!(self.lexer.match_current_token(TokenType::Arrow));
+
+            self.lexer.next_token();
+
+            let kind = try!(self.parse_type());
+
+            fields.push((name, kind));
+
+            self.lexer.next_token();
+
+            if self.lexer.tokens_remaining() > 0 && self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(fields)
+    }
+
+    // Enum variants, e.g. `Circle(float), Rect(float, float), Empty`
+    fn parse_variants(&mut self) -> Result<Vec<(String, Vec<String>)>, String> {
+        let mut variants = Vec::new();
+
+        while self.lexer.tokens_remaining() > 0 {
+            let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            let mut kinds = Vec::new();
+
+            if self.next_on_row(TokenType::LParen) {
+                self.lexer.next_token();
+
+                while try!(self.in_list(TokenType::RParen, "variant")) {
+                    kinds.push(try!(self.parse_type()));
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        self.lexer.next_token();
+                    }
+                }
+            }
+
+            variants.push((name, kinds));
+
+            self.lexer.next_token();
+
+            if self.lexer.tokens_remaining() > 0 && self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(variants)
+    }
+
+    // Invoked on the LBrace of `Point { x: 1, y: 2 }`
+    fn parse_record(&mut self, name: String) -> Result<Expression, String> {
+        let mut fields = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RBrace, "record")) {
+            let field = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            self.lexer.next_token();
+
+            try!(self.lexer.match_current_token(TokenType::Colon));
+
+            self.lexer.next_token();
+
+            fields.push((field, try!(self.parse_expression())));
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(Expression::Record(name, Box::new(fields)))
+    }
+
+    // Invoked on the LBrace of a map `{ "a": 1 }` or a set `{ 1, 2 }`, `{}` being an empty map
+    fn parse_collection(&mut self) -> Result<Expression, String> {
+        let mut entries = Vec::new();
+        let mut members = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RBrace, "map or set")) {
+            let key = try!(self.parse_nested());
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Colon {
+                if !members.is_empty() {
+                    return Err(format!("map entry in set literal: {:?}", key))
+                }
+
+                self.lexer.next_token();
+
+                entries.push((key, try!(self.parse_nested())));
+
+                self.lexer.next_token();
+            } else if !entries.is_empty() {
+                return Err(format!("set member in map literal: {:?}", key))
+            } else {
+                members.push(key);
+            }
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        match members.is_empty() {
+            true  => Ok(Expression::Map(Box::new(entries))),
+            false => Ok(Expression::Set(Box::new(members))),
+        }
+    }
+
+    // Moves onto the next token if it has type `t` and sits on the current line
+    fn next_on_row(&mut self, t: TokenType) -> bool {
+        let row = self.lexer.current_token().row;
+
+        if self.lexer.next_token() {
+            if self.lexer.tokens_remaining() > 0 {
+                let token = self.lexer.current_token();
+
+                if token.token_type == t && token.row == row {
+                    return true
+                }
+            }
+
+            self.lexer.previous_token();
+        }
+
+        false
+    }
+
+    // Moves onto the value `keyword` takes, which has to start on the same line
+    fn next_value(&mut self, keyword: &str) -> Result<(), String> {
+        let row = self.lexer.current_token().row;
+
+        if !self.lexer.next_token() || self.lexer.tokens_remaining() == 0 || self.lexer.current_token().row != row {
+            return Err(format!("expected a value after `{}` on line {}", keyword, row))
+        }
+
+        Ok(())
+    }
+
+    // Moves onto a value following the current keyword on its line, if there is one
+    fn next_on_row_value(&mut self) -> bool {
+        let row = self.lexer.current_token().row;
+
+        if self.lexer.next_token() {
+            if self.lexer.tokens_remaining() > 0 {
+                let token = self.lexer.current_token();
+                let ends  = [TokenType::End, TokenType::Else, TokenType::Catch];
+
+                if token.row == row && !ends.contains(&token.token_type) {
+                    return true
+                }
+            }
+
+            self.lexer.previous_token();
+        }
+
+        false
+    }
+
+    // Whether the list being parsed goes on until `close`, failing if the input ends first
+    fn in_list(&self, close: TokenType, what: &str) -> Result<bool, String> {
+        if self.lexer.tokens_remaining() == 0 {
+            return Err(format!("unterminated {} on line {}", what, self.lexer.current_token().row))
+        }
+
+        Ok(self.lexer.current_token().token_type != close)
+    }
+
+    fn parse_loop(&mut self, label: Option<String>) -> Result<Statement, String> {
+        match self.lexer.current_token().token_type {
+            TokenType::While => {
+                self.lexer.next_token();
+
+                let condition = try!(self.parse_expression());
+
+                self.lexer.next_token();
+
+                let body = try!(self.parse_block());
+
+                Ok(Statement::While(
+                    label,
+                    Box::new(condition),
+                    Box::new(Statement::Block(Box::new(body))),
+                ))
+            },
+
+            TokenType::For => {
+                self.lexer.next_token();
+
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+                self.lexer.next_token();
+
+                try!(self.lexer.match_current_token(TokenType::In));
+
+                self.lexer.next_token();
+
+                let iterable = try!(self.parse_expression());
+
+                self.lexer.next_token();
+
+                let body = try!(self.parse_block());
+
+                Ok(Statement::For(
+                    label,
+                    name,
+                    Box::new(iterable),
+                    Box::new(Statement::Block(Box::new(body))),
+                ))
+            },
+
+            _ => Err(format!(
+                "expected loop after label but found {:?}", self.lexer.current_token()
+            )),
+        }
+    }
+
+    // Label following `break` or `continue`, only if on the same line
+    fn parse_label(&mut self) -> Option<String> {
+        let row = self.lexer.current_token().row;
+
+        if self.lexer.next_token() {
+            if self.lexer.tokens_remaining() > 0 {
+                let token = self.lexer.current_token();
+
+                if token.token_type == TokenType::Identifier && token.row == row {
+                    return Some(token.content.clone())
+                }
+            }
+
+            self.lexer.previous_token();
+        }
+
+        None
+    }
+
+    pub fn parse_full(&mut self) -> Result<Vec<Statement>, String> {
+        let mut statement_stack = Vec::new();
+
+        loop {
+            if self.lexer.tokens_remaining() < 1 {
+                break
+            }
+
+            statement_stack.push(try!(self.parse_statement()));
+
+            self.lexer.next_token();
+        }
+
+        Ok(statement_stack)
+    }
+
+    // A `yield` in the block belongs to the function around it
+    fn parse_block(&mut self) -> Result<Vec<Statement>, String> {
+        let block_tokens = try!(self.parse_block_tokens());
+
+        let mut parser = Parser::from(Lexer::from(block_tokens));
+        let block      = try!(parser.parse_full());
+
+        self.yields |= parser.yields;
+
+        Ok(block)
+    }
+
+    // Tokens between `do` and its matching `end`
+    fn parse_block_tokens(&mut self) -> Result<Vec<Token>, String> {
+        try!(self.lexer.match_current_token(TokenType::Do));
+
+        let mut block_tokens = Vec::new();
+        let mut opened_dos   = 1;
+
+        while self.lexer.next_token() {
+            if self.lexer.current_token().token_type == TokenType::Do {
+                opened_dos += 1;     
+            } else if self.lexer.current_token().token_type == TokenType::End {
+                opened_dos -= 1;     
+            }
+
+            if opened_dos == 0 {
+                break
+            }
+
+            block_tokens.push(self.lexer.current_token().clone());
+        }
+
+        try!(self.lexer.match_current_token(TokenType::End));
+
+        Ok(block_tokens)
+    }
+
+    fn parse_arms(&mut self) -> Result<Vec<(Pattern, Option<Expression>, Expression)>, String> {
+        let mut arms = Vec::new();
+
+        while self.lexer.tokens_remaining() > 0 {
+            let pattern = try!(self.parse_pattern());
+
+            self.lexer.next_token();
+
+            let guard: Option<Expression>;
+
+            if self.lexer.current_token().token_type == TokenType::If {
+                self.lexer.next_token();
+
+                guard = Some(try!(self.parse_expression()));
+
+                self.lexer.next_token();
+            } else {
+                guard = None;
+            }
+
+            try!(self.lexer.match_current_token(TokenType::Arrow));
+
+            self.lexer.next_token();
+
+            let body = try!(self.parse_expression());
+
+            arms.push((pattern, guard, body));
+
+            self.lexer.next_token();
+        }
+
+        Ok(arms)
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, String> {
+        match self.lexer.current_token().token_type {
+            // the suffix of a literal pattern is checked but left to the type of the subject
+            TokenType::Integer | TokenType::Float => {
+                let mut number = try!(Parser::parse_number(&self.lexer.current_token_content()));
+
+                if let Expression::Typed(inner, _) = number {
+                    number = *inner;
+                }
+
+                match number {
+                    Expression::Integer(v) => Ok(Pattern::Integer(v)),
+                    Expression::Float(v)   => Ok(Pattern::Float(v)),
+                    _                      => Err(format!("number pattern out of range: {:?}", number)),
+                }
+            },
+
+            TokenType::Text => {
+                Ok(Pattern::Text(self.lexer.current_token_content()))
+            },
+
+            TokenType::Char => {
+                Ok(Pattern::Char(self.lexer.current_token_content().chars().next().unwrap()))
+            },
+
+            TokenType::True => {
+                Ok(Pattern::Bool(true))
+            },
+
+            TokenType::False => {
+                Ok(Pattern::Bool(false))
+            },
+
+            TokenType::Identifier => {
+                let name = self.lexer.current_token_content();
+
+                if self.next_on_row(TokenType::LBrace) {
+                    return self.parse_record_pattern(name)
+                }
+
+                if self.next_on_row(TokenType::LParen) {
+                    self.lexer.next_token();
+
+                    let mut stack = Vec::new();
+
+                    while try!(self.in_list(TokenType::RParen, "variant pattern")) {
+                        stack.push(try!(self.parse_pattern()));
+
+                        self.lexer.next_token();
+
+                        if self.lexer.current_token().token_type == TokenType::Comma {
+                            self.lexer.next_token();
+                        }
+                    }
+
+                    return Ok(Pattern::Variant(name, Box::new(stack)))
+                }
+
+                // a capitalised name is a variant without fields, e.g. `None`, rather than a binding
+                match &name[..] {
+                    "_" => Ok(Pattern::Wildcard),
+                    _ if name.starts_with(char::is_uppercase) => Ok(Pattern::Variant(name, Box::default())),
+                    _   => Ok(Pattern::Identifier(name)),
+                }
+            },
+
+            TokenType::LBracket => {
+                self.lexer.next_token();
+
+                let mut stack = Vec::new();
+
+                while try!(self.in_list(TokenType::RBracket, "array pattern")) {
+                    stack.push(try!(self.parse_pattern()));
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        self.lexer.next_token();
+                    }
+                }
+
+                Ok(Pattern::Array(Box::new(stack)))
+            },
+
+            TokenType::LParen => {
+                self.lexer.next_token();
+
+                let mut stack = Vec::new();
+
+                while try!(self.in_list(TokenType::RParen, "tuple pattern")) {
+                    stack.push(try!(self.parse_pattern()));
+
+                    self.lexer.next_token();
+
+                    if self.lexer.current_token().token_type == TokenType::Comma {
+                        self.lexer.next_token();
+                    }
+                }
+
+                Ok(Pattern::Tuple(Box::new(stack)))
+            },
+
+            _ => Err(format!(
+                "expected pattern but found {:?}", self.lexer.current_token()
+            )),
+        }
+    }
+
+    // Invoked on the LBrace of `Point { x: 0, y }`, where `y` is short for `y: y`
+    fn parse_record_pattern(&mut self, name: String) -> Result<Pattern, String> {
+        let mut fields = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RBrace, "record pattern")) {
+            let field = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Colon {
+                self.lexer.next_token();
+
+                fields.push((field, try!(self.parse_pattern())));
+
+                self.lexer.next_token();
+            } else {
+                fields.push((field.clone(), Pattern::Identifier(field)));
+            }
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(Pattern::Record(name, Box::new(fields)))
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, String> {
+        let expr = try!(self.parse_operand());
+
+        if self.lexer.next_token() {
+            if self.lexer.tokens_remaining() > 0 {
+                let inclusive = match self.lexer.current_token().token_type {
+                    TokenType::Range          => Some(false),
+                    TokenType::RangeInclusive => Some(true),
+                    _                         => None,
+                };
+
+                if let Some(inclusive) = inclusive {
+                    self.lexer.next_token();
+
+                    let end = try!(self.parse_operand());
+
+                    return Ok(Expression::Range(Box::new(expr), Box::new(end), inclusive))
+                }
+            }
+
+            self.lexer.previous_token();
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expression, String> {
+        let expr = try!(self.parse_word());
+
+        self.lexer.next_token();
+
+        if self.lexer.tokens_remaining() > 0 {
+            if self.lexer.current_token().token_type == TokenType::BinOp {
+                return self.parse_bin_op(expr);
+            }
+
+            self.lexer.previous_token();
+        }
+
+        Ok(expr)
+    }
+
+    // Calls, indexing and field access trailing an expression, e.g. `foo(1)[2].x`
+    fn parse_postfix(&mut self, expr: Expression) -> Result<Expression, String> {
+        let row = self.lexer.current_token().row;
+        let end = self.lexer.current_token().col;
+
+        // a call is placed at the last token of its callee, which for a plain name is the name itself
+        let position = self.lexer.current_token().position();
+
+        let callable = matches!(expr, Expression::Identifier(_) | Expression::Dot(..));
+
+        if self.lexer.next_token() {
+            if self.lexer.tokens_remaining() > 0 {
+                // whether whitespace separates this token from the expression
+                let spaced = self.lexer.current_token().start > end;
+
+                match self.lexer.current_token().token_type {
+                    // fields by name and tuple positions, e.g. `p.x` and `t.0`
+                    TokenType::Period => {
+                        self.lexer.next_token();
+
+                        let field = match self.lexer.current_token().token_type {
+                            TokenType::Integer => try!(Parser::parse_number(&self.lexer.current_token_content())),
+                            _                  => Expression::Identifier(
+                                try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone()
+                            ),
+                        };
+
+                        return self.parse_postfix(Expression::Dot(Box::new(expr), Box::new(field)))
+                    },
+
+                    // a bracket opening the next line isn't trailing anything
+                    _ if self.lexer.current_token().row != row => (),
+
+                    // `f a b` and `f (a) [b]` call `f`, binding looser than any operator in the arguments
+                    ref t if Parser::starts_argument(t)
+                              && (!Parser::is_bracket(t) || spaced && (callable || self.bare_args)) => {
+                        if callable && !self.bare_args {
+                            return self.parse_bare_call(expr, position)
+                        }
+
+                        // not a callee, or already an argument of one, so `f a b` stays a single call
+                        self.lexer.previous_token();
+
+                        return Ok(expr)
+                    },
+
+                    TokenType::LParen => {
+                        let call = try!(self.parse_caller(expr, position));
+
+                        return self.parse_postfix(call)
+                    },
+
+                    TokenType::LBracket => {
+                        self.lexer.next_token();
+
+                        let index = try!(self.parse_nested());
+
+                        self.lexer.next_token();
+
+                        try!(self.lexer.match_current_token(TokenType::RBracket));
+
+                        return self.parse_postfix(Expression::Index(Box::new(expr), Box::new(index)))
+                    },
+
+                    _ => (),
+                }
+            }
+
+            self.lexer.previous_token();
+        }
+
+        Ok(expr)
+    }
+
+    // Invoked on the LParen of `(a -> int, b = 1, rest...)`, leaving the RParen current
+    fn parse_params(&mut self) -> Result<Vec<Parameter>, String> {
+        let mut params: Vec<Parameter> = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RParen, "parameters")) {
+            if params.last().is_some_and(|p| p.variadic) {
+                return Err(format!("parameter after variadic: {:?}", self.lexer.current_token()))
+            }
+
+            let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            self.lexer.next_token();
+
+            let mut param = Parameter::new(name, None);
+
+            if self.lexer.current_token().token_type == TokenType::Ellipsis {
+                param.variadic = true;
+
+                self.lexer.next_token();
+            }
+
+            if self.lexer.current_token().token_type == TokenType::Arrow {
+                self.lexer.next_token();
+
+                param.kind = Some(try!(self.parse_type()));
+
+                self.lexer.next_token();
+            }
+
+            if self.lexer.current_token().token_type == TokenType::Assign && !param.variadic {
+                self.lexer.next_token();
+
+                param.default = Some(try!(self.parse_nested()));
+
+                self.lexer.next_token();
+            } else if !param.variadic && params.last().is_some_and(|p| p.default.is_some()) {
+                return Err(format!("parameter without default after one with: {}", param.name))
+            }
+
+            params.push(param);
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(params)
+    }
+
+    // Optional `-> type`, leaving the token after it current
+    fn parse_return_type(&mut self) -> Result<Option<String>, String> {
+        if self.lexer.current_token().token_type != TokenType::Arrow {
+            return Ok(None)
+        }
+
+        self.lexer.next_token();
+
+        let kind = try!(self.parse_type());
+
+        self.lexer.next_token();
+
+        Ok(Some(kind))
+    }
+
+    // Type such as `int`, `[T]`, `(int, text)`, `(T) -> U` or `Option[T]`, leaving its last token current
+    fn parse_type(&mut self) -> Result<String, String> {
+        match self.lexer.current_token().token_type {
+            TokenType::LBracket => {
+                self.lexer.next_token();
+
+                let element = try!(self.parse_type());
+
+                self.lexer.next_token();
+
+                try!(self.lexer.match_current_token(TokenType::RBracket));
+
+                Ok(format!("[{}]", element))
+            },
+
+            TokenType::LParen => {
+                let kinds = try!(self.parse_type_list(TokenType::RParen));
+
+                if self.next_on_row(TokenType::Arrow) {
+                    self.lexer.next_token();
+
+                    let result = try!(self.parse_type());
+
+                    return Ok(format!("({}) -> {}", kinds.join(", "), result))
+                }
+
+                Ok(format!("({})", kinds.join(", ")))
+            },
+
+            _ => {
+                let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+                if self.next_on_row(TokenType::LBracket) {
+                    let kinds = try!(self.parse_type_list(TokenType::RBracket));
+
+                    return Ok(format!("{}[{}]", name, kinds.join(", ")))
+                }
+
+                Ok(name)
+            },
+        }
+    }
+
+    // Invoked on the bracket opening a list of types, leaving the closing `close` current
+    fn parse_type_list(&mut self, close: TokenType) -> Result<Vec<String>, String> {
+        let mut kinds = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(close.clone(), "type list")) {
+            kinds.push(try!(self.parse_type()));
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(kinds)
+    }
+
+    // Optional `[T, U]` following a declared name, leaving the token after it current
+    fn parse_type_params(&mut self) -> Result<Vec<String>, String> {
+        let mut names: Vec<String> = Vec::new();
+
+        if self.lexer.current_token().token_type != TokenType::LBracket {
+            return Ok(names)
+        }
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RBracket, "type parameters")) {
+            let name = try!(self.lexer.match_current_token(TokenType::Identifier)).content.clone();
+
+            if names.contains(&name) {
+                return Err(format!("type parameter declared twice: {}", name))
+            }
+
+            names.push(name);
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        self.lexer.next_token();
+
+        Ok(names)
+    }
+
+    // Invoked when LParen is popped
+    fn parse_caller(&mut self, callee: Expression, position: Position) -> Result<Expression, String> {
+        let mut stack = Vec::new();
+        let mut named = Vec::new();
+
+        self.lexer.next_token();
+
+        while try!(self.in_list(TokenType::RParen, "arguments")) {
+            let name = self.lexer.current_token_content();
+
+            if self.lexer.current_token().token_type == TokenType::Identifier && self.next_on_row(TokenType::Colon) {
+                self.lexer.next_token();
+
+                named.push((name, try!(self.parse_nested())));
+            } else if !named.is_empty() {
+                return Err(format!("positional argument after named: {:?}", self.lexer.current_token()))
+            } else {
+                stack.push(try!(self.parse_nested()));
+            }
+
+            self.lexer.next_token();
+
+            if self.lexer.current_token().token_type == TokenType::Comma {
+                self.lexer.next_token();
+            }
+        }
+
+        Ok(Expression::Call(Box::new(callee), Box::new(stack), Box::new(named), position))
+    }
+
+    fn parse_bare_call(&mut self, callee: Expression, position: Position) -> Result<Expression, String> {
+        self.bare_args = true;
+
+        let result = self.parse_bare_args();
+
+        self.bare_args = false;
+
+        Ok(Expression::Call(Box::new(callee), Box::new(try!(result)), Box::default(), position))
+    }
+
+    // Arguments of `f a, b c`, up to the end of the line
+    fn parse_bare_args(&mut self) -> Result<Vec<Expression>, String> {
+        let mut stack = vec!(try!(self.parse_expression()));
+
+        loop {
+            let row = self.lexer.current_token().row;
+
+            if !self.lexer.next_token() {
+                break
+            }
+
+            if self.lexer.tokens_remaining() > 0 && self.lexer.current_token().row == row {
+                if self.lexer.current_token().token_type == TokenType::Comma {
+                    self.lexer.next_token();
+                }
+
+                if Parser::starts_argument(&self.lexer.current_token().token_type) {
+                    stack.push(try!(self.parse_expression()));
+
+                    continue
+                }
+            }
+
+            self.lexer.previous_token();
+            break
+        }
+
+        Ok(stack)
+    }
+
+    // Parses an expression inside brackets, where paren-less calls are allowed again
+    fn parse_nested(&mut self) -> Result<Expression, String> {
+        let bare_args = self.bare_args;
+
+        self.bare_args = false;
+
+        let result = self.parse_expression();
+
+        self.bare_args = bare_args;
+
+        result
+    }
+
+    fn starts_argument(t: &TokenType) -> bool {
+        match *t {
+            TokenType::Integer    |
+            TokenType::Float      |
+            TokenType::Text       |
+            TokenType::Char       |
+            TokenType::Identifier |
+            TokenType::True       |
+            TokenType::False      => true,
+            _                     => Parser::is_bracket(t),
+        }
+    }
+
+    fn is_bracket(t: &TokenType) -> bool {
+        *t == TokenType::LParen || *t == TokenType::LBracket
     }
 }
\ No newline at end of file
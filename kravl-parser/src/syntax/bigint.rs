@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops;
+
+/* Integers too large for an i64,
+ * stored as base 10^9 limbs with the least significant first.
+ */
+
+const BASE: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    limbs:    Vec<u32>,
+}
+
+// Drops the most significant zero limbs, zero having none at all
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+
+    limbs
+}
+
+fn compare_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut carry  = 0;
+
+    for i in 0 .. a.len().max(b.len()) {
+        let value = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+
+        result.push((value % BASE) as u32);
+        carry = value / BASE;
+    }
+
+    result.push(carry as u32);
+
+    trim(result)
+}
+
+// `a - b`, where `a` is at least `b`
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::new();
+    let mut borrow = 0;
+
+    for (i, &l) in a.iter().enumerate() {
+        let mut value = i64::from(l) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+
+        borrow = if value < 0 { 1 } else { 0 };
+        value += borrow * BASE as i64;
+
+        result.push(value as u32);
+    }
+
+    trim(result)
+}
+
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec!(0u64; a.len() + b.len());
+
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+
+        for (j, &y) in b.iter().enumerate() {
+            let value = result[i + j] + u64::from(x) * u64::from(y) + carry;
+
+            result[i + j] = value % BASE;
+            carry = value / BASE;
+        }
+
+        result[i + b.len()] = carry;
+    }
+
+    trim(result.into_iter().map(|l| l as u32).collect())
+}
+
+// `a / b` rounded towards zero, where `b` isn't zero
+fn div_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut quotient  = vec!(0; a.len());
+    let mut remainder = Vec::new();
+
+    for i in (0 .. a.len()).rev() {
+        remainder.insert(0, a[i]);
+        remainder = trim(remainder);
+
+        // the largest limb `d` for which `b * d` still fits the remainder
+        let (mut low, mut high) = (0, BASE as u32 - 1);
+
+        while low < high {
+            let mid = high - (high - low) / 2;
+
+            match compare_limbs(&mul_limbs(b, &[mid]), &remainder) {
+                Ordering::Greater => high = mid - 1,
+                _                 => low  = mid,
+            }
+        }
+
+        remainder   = sub_limbs(&remainder, &mul_limbs(b, &[low]));
+        quotient[i] = low;
+    }
+
+    trim(quotient)
+}
+
+impl BigInt {
+    fn new(negative: bool, limbs: Vec<u32>) -> BigInt {
+        let limbs = trim(limbs);
+
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs:    limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    // The same value as an i64, None if it doesn't fit one
+    pub fn to_i64(&self) -> Option<i64> {
+        // three limbs exceed an i64 but not an i128
+        if self.limbs.len() > 3 {
+            return None
+        }
+
+        let magnitude = self.limbs.iter().rev().fold(0i128, |v, &l| v * i128::from(BASE) + i128::from(l));
+
+        i64::try_from(if self.negative { -magnitude } else { magnitude }).ok()
+    }
+
+    // Reads `digits` in `radix`, None if one of them isn't a digit
+    pub fn from_str_radix(digits: &str, radix: u32, negative: bool) -> Option<BigInt> {
+        if digits.is_empty() {
+            return None
+        }
+
+        let mut limbs: Vec<u32> = vec!(0);
+
+        for c in digits.chars() {
+            let mut carry = match c.to_digit(radix) {
+                Some(d) => u64::from(d),
+                None    => return None,
+            };
+
+            for limb in &mut limbs {
+                let value = u64::from(*limb) * u64::from(radix) + carry;
+
+                *limb = (value % BASE) as u32;
+                carry = value / BASE;
+            }
+
+            if carry > 0 {
+                limbs.push(carry as u32);
+            }
+        }
+
+        Some(BigInt::new(negative, limbs))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> BigInt {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs     = Vec::new();
+
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+
+        BigInt::new(value < 0, limbs)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true)  => Ordering::Greater,
+            (true, false)  => Ordering::Less,
+            (false, false) => compare_limbs(&self.limbs, &other.limbs),
+            (true, true)   => compare_limbs(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs)
+    }
+}
+
+impl ops::Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_limbs(&self.limbs, &other.limbs))
+        }
+
+        // signs differ, so the larger magnitude keeps its sign
+        match compare_limbs(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_limbs(&other.limbs, &self.limbs)),
+            _              => BigInt::new(self.negative, sub_limbs(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl ops::Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        ops::Add::add(self, -other)
+    }
+}
+
+impl ops::Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::new(self.negative != other.negative, mul_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+// Rounds towards zero like integer division on i64, `other` being non-zero
+impl ops::Div for BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: BigInt) -> BigInt {
+        BigInt::new(self.negative != other.negative, div_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            try!(write!(f, "-"));
+        }
+
+        let mut limbs = self.limbs.iter().rev().skip_while(|&&l| l == 0);
+
+        match limbs.next() {
+            Some(l) => try!(write!(f, "{}", l)),
+            None    => return write!(f, "0"),
+        }
+
+        for l in limbs {
+            try!(write!(f, "{:09}", l));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use syntax::tokens::BinOp;
+use syntax::bigint::BigInt;
+use syntax::ast::{
+    Statement,
+    Expression,
+    Parameter,
+};
+
+/* Folds `const` declarations into literals at compile time,
+ * running the `const define` functions they call.
+ * Integers overflowing an i64 go on as big integers,
+ * unless a suffix fixed their type.
+ */
+
+const MAX_DEPTH: usize = 256;
+
+// How a statement in a const function finished
+enum Flow {
+    Next(Option<Expression>),
+    Return(Expression),
+}
+
+pub struct Evaluator {
+    constants: HashMap<String, Expression>,
+    functions: HashMap<String, (Vec<Parameter>, Vec<Statement>)>,
+    depth:     usize,
+}
+
+impl Evaluator {
+    pub fn new() -> Evaluator {
+        Evaluator {
+            constants: HashMap::new(),
+            functions: HashMap::new(),
+            depth:     0,
+        }
+    }
+
+    // Replaces the value of every top level `const` with the literal it evaluates to
+    pub fn fold(&mut self, stack: Vec<Statement>) -> Result<Vec<Statement>, String> {
+        stack.into_iter().map(|s| self.fold_statement(s)).collect()
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Result<Statement, String> {
+        match statement {
+            Statement::Const(name, expr) => {
+                if let Expression::Definition(_, _, ref params, ref body, _, _) = *expr {
+                    self.functions.insert(name.clone(), (params.to_vec(), body.to_vec()));
+                } else {
+                    let value = match self.evaluate(&expr, &HashMap::new()) {
+                        Ok(v)  => v,
+                        Err(e) => return Err(format!("const {}: {}", name, e)),
+                    };
+
+                    self.constants.insert(name.clone(), value.clone());
+
+                    return Ok(Statement::Const(name, Box::new(value)))
+                }
+
+                Ok(Statement::Const(name, expr))
+            },
+
+            Statement::Public(statement) => Ok(Statement::Public(Box::new(try!(self.fold_statement(*statement))))),
+
+            s => Ok(s),
+        }
+    }
+
+    fn evaluate_all(&mut self, stack: &[Expression], locals: &HashMap<String, Expression>) -> Result<Vec<Expression>, String> {
+        let mut result = Vec::new();
+
+        for e in stack {
+            result.push(try!(self.evaluate(e, locals)));
+        }
+
+        Ok(result)
+    }
+
+    fn evaluate(&mut self, expression: &Expression, locals: &HashMap<String, Expression>) -> Result<Expression, String> {
+        match *expression {
+            Expression::Integer(_)    |
+            Expression::BigInteger(_) |
+            Expression::Float(_)      |
+            Expression::Text(_)       |
+            Expression::Char(_)       |
+            Expression::Bool(_)       => Ok(expression.clone()),
+
+            Expression::Typed(ref expr, ref kind) => {
+                Ok(Expression::Typed(Box::new(try!(self.evaluate(expr, locals))), kind.clone()))
+            },
+
+            Expression::Identifier(ref name) => match locals.get(name).or_else(|| self.constants.get(name)) {
+                Some(value) => Ok(value.clone()),
+                None        => Err(format!("`{}` is not a constant", name)),
+            },
+
+            Expression::Array(ref content) => Ok(Expression::Array(Box::new(try!(self.evaluate_all(content, locals))))),
+            Expression::Tuple(ref content) => Ok(Expression::Tuple(Box::new(try!(self.evaluate_all(content, locals))))),
+
+            Expression::Operation(ref left, ref op, ref right, _) => {
+                let left  = try!(self.evaluate(left, locals));
+                let right = try!(self.evaluate(right, locals));
+
+                Evaluator::operation(left, op, right)
+            },
+
+            Expression::Call(ref callee, ref args, ref named, _) => match **callee {
+                Expression::Identifier(ref name) if self.functions.contains_key(name) => {
+                    let args = try!(self.evaluate_all(args, locals));
+
+                    let mut named_args = Vec::new();
+
+                    for (n, a) in named.iter() {
+                        named_args.push((n.clone(), try!(self.evaluate(a, locals))));
+                    }
+
+                    self.call(name, args, named_args)
+                },
+
+                _ => Err(format!("not a const function: {:?}", callee)),
+            },
+
+            // a const function that throws fails the constant using it
+            Expression::Throw(ref value, position) => Err(format!(
+                "{:?} thrown at line {}, column {}", try!(self.evaluate(value, locals)), position.0, position.1
+            )),
+
+            _ => Err(format!("can't be evaluated at compile time: {:?}", expression)),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Expression>, named: Vec<(String, Expression)>) -> Result<Expression, String> {
+        let (params, body) = self.functions[name].clone();
+
+        if self.depth == MAX_DEPTH {
+            return Err(format!("calls to {} nest too deeply", name))
+        }
+
+        let mut locals = HashMap::new();
+
+        for (i, p) in params.iter().enumerate() {
+            let value = if p.variadic {
+                Expression::Array(Box::new(args.iter().skip(i).cloned().collect()))
+            } else if let Some(arg) = args.get(i) {
+                arg.clone()
+            } else if let Some((_, arg)) = named.iter().find(|(n, _)| *n == p.name) {
+                arg.clone()
+            } else if let Some(ref default) = p.default {
+                try!(self.evaluate(default, &HashMap::new()))
+            } else {
+                return Err(format!("missing argument `{}` to {}", p.name, name))
+            };
+
+            locals.insert(p.name.clone(), value);
+        }
+
+        self.depth += 1;
+
+        let result = self.run(&body, &mut locals);
+
+        self.depth -= 1;
+
+        match try!(result) {
+            Flow::Next(Some(value)) |
+            Flow::Return(value)     => Ok(value),
+            Flow::Next(None)        => Err(format!("{} gives no value", name)),
+        }
+    }
+
+    fn run(&mut self, stack: &[Statement], locals: &mut HashMap<String, Expression>) -> Result<Flow, String> {
+        let mut last = None;
+
+        for s in stack {
+            match try!(self.execute(s, locals)) {
+                Flow::Next(value) => last = value,
+                flow              => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next(last))
+    }
+
+    // Runs `stack` in a scope of its own, keeping what it assigned to the locals around it
+    fn run_block(&mut self, stack: &[Statement], locals: &mut HashMap<String, Expression>) -> Result<Flow, String> {
+        let mut inner = locals.clone();
+        let flow      = try!(self.run(stack, &mut inner));
+
+        let mut shadowed = Vec::new();
+
+        for s in stack {
+            match *s {
+                Statement::Variable(ref name, ..)   |
+                Statement::Const(ref name, _)       => shadowed.push(name),
+                Statement::Variables(ref names, ..) => shadowed.extend(names.iter()),
+                _                                   => (),
+            }
+        }
+
+        for (name, value) in locals.iter_mut() {
+            if !shadowed.contains(&name) {
+                *value = inner[name].clone();
+            }
+        }
+
+        Ok(flow)
+    }
+
+    // Only locals can change while evaluating, constants being fixed once folded
+    fn assign(name: &str, value: Expression, locals: &mut HashMap<String, Expression>) -> Result<(), String> {
+        match locals.get_mut(name) {
+            Some(local) => {
+                *local = value;
+                Ok(())
+            },
+            None => Err(format!("can't assign to `{}` at compile time", name)),
+        }
+    }
+
+    fn execute(&mut self, statement: &Statement, locals: &mut HashMap<String, Expression>) -> Result<Flow, String> {
+        match *statement {
+            Statement::Variable(ref name, _, ref expr) => {
+                let value = try!(self.evaluate(expr, locals));
+
+                locals.insert(name.clone(), value);
+
+                Ok(Flow::Next(None))
+            },
+
+            Statement::Variables(ref names, _, ref expr) => match try!(self.evaluate(expr, locals)) {
+                Expression::Tuple(ref values) if values.len() == names.len() => {
+                    for (name, value) in names.iter().zip(values.iter()) {
+                        locals.insert(name.clone(), value.clone());
+                    }
+
+                    Ok(Flow::Next(None))
+                },
+
+                value => Err(format!("can't bind {} names to {:?}", names.len(), value)),
+            },
+
+            Statement::Assignment(ref name, ref expr) => {
+                let value = try!(self.evaluate(expr, locals));
+
+                try!(Evaluator::assign(name, value, locals));
+
+                Ok(Flow::Next(None))
+            },
+
+            Statement::Expression(ref expr) => match **expr {
+                // `return` alone gives unit, `()`
+                Expression::Return(Some(ref value)) => Ok(Flow::Return(try!(self.evaluate(value, locals)))),
+                Expression::Return(None)            => Ok(Flow::Return(Expression::Tuple(Box::default()))),
+
+                // `x += 1` on a local is `x = x + 1`
+                Expression::CompoundAssignment(ref target, ref op, ref value) => match **target {
+                    Expression::Identifier(ref name) => {
+                        let left  = try!(self.evaluate(target, locals));
+                        let right = try!(self.evaluate(value, locals));
+
+                        try!(Evaluator::assign(name, try!(Evaluator::operation(left, op, right)), locals));
+
+                        Ok(Flow::Next(None))
+                    },
+
+                    _ => Err(format!("can't be evaluated at compile time: {:?}", expr)),
+                },
+
+                ref e => Ok(Flow::Next(Some(try!(self.evaluate(e, locals))))),
+            },
+
+            Statement::Block(ref stack)            |
+            Statement::Expansion(_, _, _, ref stack) => self.run_block(stack, locals),
+
+            Statement::If(ref condition, ref body) => match try!(self.condition(condition, locals)) {
+                true  => self.execute(body, locals),
+                false => Ok(Flow::Next(None)),
+            },
+
+            Statement::IfElse(ref condition, ref body, ref else_body) => match try!(self.condition(condition, locals)) {
+                true  => self.execute(body, locals),
+                false => self.execute(else_body, locals),
+            },
+
+            Statement::Try(_, _, _, position) => Err(format!(
+                "`try` at line {}, column {} can't be evaluated at compile time", position.0, position.1
+            )),
+
+            _ => Err(format!("can't be evaluated at compile time: {:?}", statement)),
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression, locals: &HashMap<String, Expression>) -> Result<bool, String> {
+        match try!(self.evaluate(condition, locals)) {
+            Expression::Bool(b) => Ok(b),
+            value               => Err(format!("condition isn't a bool: {:?}", value)),
+        }
+    }
+
+    fn operation(left: Expression, op: &BinOp, right: Expression) -> Result<Expression, String> {
+        // an operand with a suffix gives its type to the other one and to the result
+        let kind = match (Evaluator::kind(&left), Evaluator::kind(&right)) {
+            (Some(a), Some(b)) if a != b => return Err(format!("can't apply {:?} to {} and {}", op, a, b)),
+            (a, b)                       => a.or(b),
+        };
+
+        let left  = Evaluator::untyped(left);
+        let right = Evaluator::untyped(right);
+
+        if let Some(result) = Evaluator::compare(&left, op, &right) {
+            return Ok(Expression::Bool(result))
+        }
+
+        let value = try!(Evaluator::arithmetic(left, op, right));
+
+        match kind {
+            Some(kind) => Evaluator::typed(value, kind),
+            None       => Ok(value),
+        }
+    }
+
+    fn arithmetic(left: Expression, op: &BinOp, right: Expression) -> Result<Expression, String> {
+        match (left, right) {
+            (Expression::Integer(a), Expression::Integer(b)) => {
+                let value = match *op {
+                    BinOp::Plus           => a.checked_add(b),
+                    BinOp::Minus          => a.checked_sub(b),
+                    BinOp::Mul            => a.checked_mul(b),
+                    BinOp::Div if b == 0  => return Err(String::from("division by zero")),
+                    BinOp::Div            => a.checked_div(b),
+                    _                     => return Err(format!("can't apply {:?} to integers", op)),
+                };
+
+                match value {
+                    Some(v) => Ok(Expression::Integer(v)),
+                    None    => Evaluator::big(BigInt::from(a), op, BigInt::from(b)),
+                }
+            },
+
+            (Expression::Integer(a), Expression::BigInteger(b))    => Evaluator::big(BigInt::from(a), op, b),
+            (Expression::BigInteger(a), Expression::Integer(b))    => Evaluator::big(a, op, BigInt::from(b)),
+            (Expression::BigInteger(a), Expression::BigInteger(b)) => Evaluator::big(a, op, b),
+
+            (Expression::Float(a), Expression::Float(b)) => match *op {
+                BinOp::Plus  => Ok(Expression::Float(a + b)),
+                BinOp::Minus => Ok(Expression::Float(a - b)),
+                BinOp::Mul   => Ok(Expression::Float(a * b)),
+                BinOp::Div   => Ok(Expression::Float(a / b)),
+                _            => Err(format!("can't apply {:?} to floats", op)),
+            },
+
+            (Expression::Text(a), Expression::Text(b)) => match *op {
+                BinOp::Plus => Ok(Expression::Text(a + &b)),
+                _           => Err(format!("can't apply {:?} to text", op)),
+            },
+
+            (left, right) => Err(format!("can't apply {:?} to {:?} and {:?}", op, left, right)),
+        }
+    }
+
+    // Integer arithmetic past an i64, going back to one when the result fits
+    fn big(a: BigInt, op: &BinOp, b: BigInt) -> Result<Expression, String> {
+        let value = match *op {
+            BinOp::Plus               => a + b,
+            BinOp::Minus              => a - b,
+            BinOp::Mul                => a * b,
+            BinOp::Div if b.is_zero() => return Err(String::from("division by zero")),
+            BinOp::Div                => a / b,
+            _                         => return Err(format!("can't apply {:?} to integers", op)),
+        };
+
+        match value.to_i64() {
+            Some(v) => Ok(Expression::Integer(v)),
+            None    => Ok(Expression::BigInteger(value)),
+        }
+    }
+
+    // Wraps `value` back into the type `kind`, failing if it doesn't fit
+    fn typed(value: Expression, kind: String) -> Result<Expression, String> {
+        let fits = match (&value, &kind[..]) {
+            (&Expression::Integer(v), "i32") => i32::try_from(v).is_ok(),
+            (&Expression::Integer(v), "u8")  => u8::try_from(v).is_ok(),
+            (&Expression::Integer(_), "i64") => true,
+            (&Expression::Float(v), "f32")   => v.abs() <= f64::from(f32::MAX),
+            (&Expression::Float(_), "f64")   => true,
+            _                                => false,
+        };
+
+        match fits {
+            true  => Ok(Expression::Typed(Box::new(value), kind)),
+            false => Err(format!("overflow: {:?} doesn't fit {}", value, kind)),
+        }
+    }
+
+    // Result of comparing two literals of the same kind, None if `op` doesn't compare
+    fn compare(left: &Expression, op: &BinOp, right: &Expression) -> Option<bool> {
+        let ordering = match (left, right) {
+            (Expression::Integer(a), Expression::Integer(b))       => a.partial_cmp(b),
+            (Expression::Integer(a), Expression::BigInteger(b))    => BigInt::from(*a).partial_cmp(b),
+            (Expression::BigInteger(a), Expression::Integer(b))    => a.partial_cmp(&BigInt::from(*b)),
+            (Expression::BigInteger(a), Expression::BigInteger(b)) => a.partial_cmp(b),
+            (Expression::Float(a), Expression::Float(b))           => a.partial_cmp(b),
+            (Expression::Text(a), Expression::Text(b))             => a.partial_cmp(b),
+            (Expression::Char(a), Expression::Char(b))             => a.partial_cmp(b),
+            (Expression::Bool(a), Expression::Bool(b))             => a.partial_cmp(b),
+            _                                                      => None,
+        }?;
+
+        match *op {
+            BinOp::Equal    => Some(ordering == Ordering::Equal),
+            BinOp::NotEqual => Some(ordering != Ordering::Equal),
+            BinOp::Lt       => Some(ordering == Ordering::Less),
+            BinOp::Gt       => Some(ordering == Ordering::Greater),
+            BinOp::LtEqual  => Some(ordering != Ordering::Greater),
+            BinOp::GtEqual  => Some(ordering != Ordering::Less),
+            _               => None,
+        }
+    }
+
+    // Type given by a literal's suffix, e.g. `u8` in `200u8`
+    fn kind(expression: &Expression) -> Option<String> {
+        match *expression {
+            Expression::Typed(_, ref kind) => Some(kind.clone()),
+            _                              => None,
+        }
+    }
+
+    fn untyped(expression: Expression) -> Expression {
+        match expression {
+            Expression::Typed(expr, _) => *expr,
+            e                          => e,
+        }
+    }
+}
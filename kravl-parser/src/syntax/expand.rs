@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+
+use syntax::ast::{
+    Statement,
+    Expression,
+    Pattern,
+    Parameter,
+    Position,
+};
+
+/* Expands macro calls between parsing and resolution.
+ * Names declared inside a macro body are renamed apart,
+ * so they neither capture nor shadow names at the call site.
+ * A macro is visible from its definition to the end of the enclosing block.
+ */
+
+const MAX_DEPTH: usize = 64;
+
+// `error` raised inside the expansion of the macro `name`, called at `call`
+pub fn in_expansion(error: String, name: &str, call: Position, defined: Position) -> String {
+    format!(
+        "{}\n    in expansion of macro `{}` called at line {}, column {}, defined at line {}, column {}",
+        error, name, call.0, call.1, defined.0, defined.1
+    )
+}
+
+#[derive(Clone)]
+struct Macro {
+    params:   Vec<String>,
+    body:     Vec<Statement>,
+    position: Position,
+}
+
+// Arguments and renamed declarations of the macro body being expanded, if any
+#[derive(Clone, Default)]
+struct Scope {
+    args:     HashMap<String, Expression>,
+    renames:  HashMap<String, String>,
+    hygienic: bool,
+    id:       usize,
+}
+
+pub struct Expander {
+    macros:     HashMap<String, Macro>,
+    expansions: usize,
+    depth:      usize,
+}
+
+impl Expander {
+    pub fn new() -> Expander {
+        Expander {
+            macros:     HashMap::new(),
+            expansions: 0,
+            depth:      0,
+        }
+    }
+
+    // Removes macro definitions from `stack` and expands every call to them
+    pub fn expand(&mut self, stack: Vec<Statement>) -> Result<Vec<Statement>, String> {
+        self.statements(stack, &mut Scope::default())
+    }
+
+    // Name a macro body declares, made unique to this expansion
+    fn declare(&self, name: String, scope: &mut Scope) -> String {
+        if !scope.hygienic {
+            return name
+        }
+
+        let fresh = format!("{}@{}", name, scope.id);
+
+        scope.args.remove(&name);
+        scope.renames.insert(name, fresh.clone());
+
+        fresh
+    }
+
+    fn rename(&self, name: String, scope: &Scope) -> String {
+        match scope.renames.get(&name) {
+            Some(fresh) => fresh.clone(),
+            None        => name,
+        }
+    }
+
+    // Name of the macro `callee` refers to, if it is one
+    fn macro_name(&self, callee: &Expression) -> Option<String> {
+        match *callee {
+            Expression::Identifier(ref name) if self.macros.contains_key(name) => Some(name.clone()),
+            _                                                                 => None,
+        }
+    }
+
+    // Body of the macro `name` called at `call`, arguments substituted and declarations renamed
+    fn expand_call(&mut self, name: String, args: Vec<Expression>, named: Vec<(String, Expression)>, call: Position, scope: &mut Scope) -> Result<Vec<Statement>, String> {
+        let Macro { params, body, position } = self.macros[&name].clone();
+
+        if !named.is_empty() {
+            return Err(format!("macro `{}` takes no named arguments", name))
+        }
+
+        if args.len() != params.len() {
+            return Err(format!(
+                "macro `{}` takes {} arguments but found {}", name, params.len(), args.len()
+            ))
+        }
+
+        if self.depth == MAX_DEPTH {
+            return Err(format!("macro `{}` expands too deeply", name))
+        }
+
+        let mut substitutes = HashMap::new();
+
+        for (p, a) in params.into_iter().zip(args) {
+            substitutes.insert(p, try!(self.expression(a, scope)));
+        }
+
+        self.expansions += 1;
+
+        let mut inner = Scope {
+            args:     substitutes,
+            renames:  HashMap::new(),
+            hygienic: true,
+            id:       self.expansions,
+        };
+
+        self.depth += 1;
+
+        let result = self.statements(body, &mut inner);
+
+        self.depth -= 1;
+
+        result.map_err(|e| in_expansion(e, &name, call, position))
+    }
+
+    // Macros defined in `stack` go out of scope with it
+    fn statements(&mut self, stack: Vec<Statement>, scope: &mut Scope) -> Result<Vec<Statement>, String> {
+        let outer  = self.macros.clone();
+        let result = self.block(stack, scope);
+
+        self.macros = outer;
+
+        result
+    }
+
+    fn block(&mut self, stack: Vec<Statement>, scope: &mut Scope) -> Result<Vec<Statement>, String> {
+        let mut result = Vec::new();
+
+        for s in stack {
+            match s {
+                Statement::Macro(name, params, body, position) => {
+                    self.macros.insert(name, Macro {
+                        params:   *params,
+                        body:     *body,
+                        position: position,
+                    });
+                },
+
+                s => result.push(try!(self.statement(s, scope))),
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Rewrites a function in its own scope, defaults being evaluated outside of it
+    fn function(&mut self, params: Vec<Parameter>, body: Vec<Statement>, scope: &mut Scope) -> Result<(Vec<Parameter>, Vec<Statement>), String> {
+        let mut inner  = scope.clone();
+        let mut result = Vec::new();
+
+        for mut p in params {
+            if let Some(default) = p.default {
+                p.default = Some(try!(self.expression(default, scope)));
+            }
+
+            p.name = self.declare(p.name, &mut inner);
+
+            result.push(p);
+        }
+
+        let body = try!(self.statements(body, &mut inner));
+
+        Ok((result, body))
+    }
+
+    fn statement(&mut self, statement: Statement, scope: &mut Scope) -> Result<Statement, String> {
+        Ok(match statement {
+            Statement::If(condition, body) => {
+                let condition = try!(self.expression(*condition, scope));
+
+                Statement::If(Box::new(condition), Box::new(try!(self.statement(*body, scope))))
+            },
+
+            Statement::IfElse(condition, body, else_body) => {
+                let condition = try!(self.expression(*condition, scope));
+                let body      = try!(self.statement(*body, scope));
+
+                Statement::IfElse(Box::new(condition), Box::new(body), Box::new(try!(self.statement(*else_body, scope))))
+            },
+
+            Statement::Try(body, name, handler, position) => {
+                let body      = try!(self.statement(*body, scope));
+                let mut inner = scope.clone();
+                let name      = name.map(|n| self.declare(n, &mut inner));
+
+                Statement::Try(Box::new(body), name, Box::new(try!(self.statement(*handler, &mut inner))), position)
+            },
+
+            Statement::Defer(body) => Statement::Defer(Box::new(try!(self.statement(*body, scope)))),
+
+            Statement::While(label, condition, body) => {
+                let condition = try!(self.expression(*condition, scope));
+
+                Statement::While(label, Box::new(condition), Box::new(try!(self.statement(*body, scope))))
+            },
+
+            Statement::For(label, name, iterable, body) => {
+                let iterable  = try!(self.expression(*iterable, scope));
+                let mut inner = scope.clone();
+                let name      = self.declare(name, &mut inner);
+
+                Statement::For(label, name, Box::new(iterable), Box::new(try!(self.statement(*body, &mut inner))))
+            },
+
+            Statement::Variables(names, mutable, expr) => {
+                let expr  = try!(self.expression(*expr, scope));
+                let names = names.into_iter().map(|n| self.declare(n, scope)).collect();
+
+                Statement::Variables(Box::new(names), mutable, Box::new(expr))
+            },
+
+            Statement::Variable(name, mutable, expr) => {
+                let expr = try!(self.expression(*expr, scope));
+
+                Statement::Variable(self.declare(name, scope), mutable, Box::new(expr))
+            },
+
+            Statement::Const(name, expr) => {
+                let expr = try!(self.expression(*expr, scope));
+
+                Statement::Const(self.declare(name, scope), Box::new(expr))
+            },
+
+            Statement::Block(stack) => {
+                Statement::Block(Box::new(try!(self.statements(*stack, &mut scope.clone()))))
+            },
+
+            // a macro called as a statement expands in a block of its own,
+            // which remembers the macro for errors found after expansion
+            Statement::Expression(expr) => match *expr {
+                Expression::Call(callee, args, named, position) => {
+                    if let Some(name) = self.macro_name(&callee) {
+                        let defined = self.macros[&name].position;
+                        let body    = try!(self.expand_call(name.clone(), *args, *named, position, scope));
+
+                        Statement::Expansion(name, position, defined, Box::new(body))
+                    } else {
+                        Statement::Expression(Box::new(try!(self.expression(Expression::Call(callee, args, named, position), scope))))
+                    }
+                },
+
+                expr => Statement::Expression(Box::new(try!(self.expression(expr, scope)))),
+            },
+
+            Statement::Assignment(name, expr) => {
+                let expr = try!(self.expression(*expr, scope));
+
+                match scope.args.get(&name).cloned() {
+                    Some(Expression::Identifier(target)) => Statement::Assignment(target, Box::new(expr)),
+
+                    Some(target) => Statement::Expression(Box::new(
+                        Expression::Assignment(Box::new(target), Box::new(expr))
+                    )),
+
+                    None => Statement::Assignment(self.rename(name, scope), Box::new(expr)),
+                }
+            },
+
+            Statement::Trait(name, type_params, methods) => {
+                let mut result = Vec::new();
+
+                for mut m in *methods {
+                    if let Some(body) = m.body {
+                        let (params, body) = try!(self.function(m.params, body, scope));
+
+                        m.params = params;
+                        m.body   = Some(body);
+                    }
+
+                    result.push(m);
+                }
+
+                Statement::Trait(name, type_params, Box::new(result))
+            },
+
+            // methods keep their names, being reached through their receiver
+            Statement::Impl(name, kind, methods) => {
+                let mut result = Vec::new();
+
+                for s in *methods {
+                    match s {
+                        Statement::Expression(expr) => match *expr {
+                            Expression::Definition(method, type_params, params, body, ret_type, generator) => {
+                                let (params, body) = try!(self.function(*params, *body, scope));
+
+                                result.push(Statement::Expression(Box::new(Expression::Definition(
+                                    method, type_params, Box::new(params), Box::new(body), ret_type, generator
+                                ))));
+                            },
+
+                            expr => result.push(Statement::Expression(Box::new(expr))),
+                        },
+
+                        s => result.push(s),
+                    }
+                }
+
+                Statement::Impl(name, kind, Box::new(result))
+            },
+
+            Statement::Public(statement) => Statement::Public(Box::new(try!(self.statement(*statement, scope)))),
+
+            Statement::Macro(name, ..) => return Err(format!("macro `{}` must be declared as a statement of its own", name)),
+
+            s @ Statement::Break(_)      |
+            s @ Statement::Continue(_)   |
+            s @ Statement::Struct(..)    |
+            s @ Statement::Enum(..)      |
+            s @ Statement::Import(..)    |
+            s @ Statement::Expansion(..) => s,
+        })
+    }
+
+    fn expressions(&mut self, stack: Vec<Expression>, scope: &mut Scope) -> Result<Vec<Expression>, String> {
+        let mut result = Vec::new();
+
+        for e in stack {
+            result.push(try!(self.expression(e, scope)));
+        }
+
+        Ok(result)
+    }
+
+    fn expression(&mut self, expression: Expression, scope: &mut Scope) -> Result<Expression, String> {
+        Ok(match expression {
+            Expression::Identifier(name) => match scope.args.get(&name) {
+                Some(arg) => arg.clone(),
+                None      => Expression::Identifier(self.rename(name, scope)),
+            },
+
+            // a macro called as an expression has to expand to a single one
+            Expression::Call(callee, args, named, position) => {
+                if let Some(name) = self.macro_name(&callee) {
+                    let mut body = try!(self.expand_call(name, *args, *named, position, scope));
+
+                    match body.pop() {
+                        Some(Statement::Expression(expr)) if body.is_empty() => *expr,
+                        _ => return Err(String::from("macro expanding to statements used as an expression")),
+                    }
+                } else {
+                    let callee = try!(self.expression(*callee, scope));
+                    let args   = try!(self.expressions(*args, scope));
+
+                    let mut result = Vec::new();
+
+                    for (n, a) in *named {
+                        result.push((n, try!(self.expression(a, scope))));
+                    }
+
+                    Expression::Call(Box::new(callee), Box::new(args), Box::new(result), position)
+                }
+            },
+
+            Expression::Assignment(target, expr) => {
+                let target = try!(self.expression(*target, scope));
+
+                Expression::Assignment(Box::new(target), Box::new(try!(self.expression(*expr, scope))))
+            },
+
+            Expression::CompoundAssignment(target, op, expr) => {
+                let target = try!(self.expression(*target, scope));
+
+                Expression::CompoundAssignment(Box::new(target), op, Box::new(try!(self.expression(*expr, scope))))
+            },
+
+            // the right side of a dot is a field, not a name in scope
+            Expression::Dot(left, field) => Expression::Dot(Box::new(try!(self.expression(*left, scope))), field),
+
+            Expression::Index(left, index) => {
+                let left = try!(self.expression(*left, scope));
+
+                Expression::Index(Box::new(left), Box::new(try!(self.expression(*index, scope))))
+            },
+
+            Expression::Range(start, end, inclusive) => {
+                let start = try!(self.expression(*start, scope));
+
+                Expression::Range(Box::new(start), Box::new(try!(self.expression(*end, scope))), inclusive)
+            },
+
+            Expression::Operation(left, op, right, position) => {
+                let left = try!(self.expression(*left, scope));
+
+                Expression::Operation(Box::new(left), op, Box::new(try!(self.expression(*right, scope))), position)
+            },
+
+            Expression::Array(content) => Expression::Array(Box::new(try!(self.expressions(*content, scope)))),
+            Expression::Tuple(content) => Expression::Tuple(Box::new(try!(self.expressions(*content, scope)))),
+            Expression::Set(content)   => Expression::Set(Box::new(try!(self.expressions(*content, scope)))),
+
+            Expression::Map(entries) => {
+                let mut result = Vec::new();
+
+                for (k, v) in *entries {
+                    let k = try!(self.expression(k, scope));
+
+                    result.push((k, try!(self.expression(v, scope))));
+                }
+
+                Expression::Map(Box::new(result))
+            },
+
+            Expression::Record(name, fields) => {
+                let mut result = Vec::new();
+
+                for (f, e) in *fields {
+                    result.push((f, try!(self.expression(e, scope))));
+                }
+
+                Expression::Record(name, Box::new(result))
+            },
+
+            Expression::Typed(expr, kind)     => Expression::Typed(Box::new(try!(self.expression(*expr, scope))), kind),
+            Expression::Throw(expr, position) => Expression::Throw(Box::new(try!(self.expression(*expr, scope))), position),
+            Expression::Yield(expr)           => Expression::Yield(Box::new(try!(self.expression(*expr, scope)))),
+
+            Expression::Return(expr) => match expr {
+                Some(expr) => Expression::Return(Some(Box::new(try!(self.expression(*expr, scope))))),
+                None       => Expression::Return(None),
+            },
+
+            Expression::Definition(name, type_params, params, body, ret_type, generator) => {
+                let name = name.map(|n| self.declare(n, scope));
+
+                let (params, body) = try!(self.function(*params, *body, scope));
+
+                Expression::Definition(name, type_params, Box::new(params), Box::new(body), ret_type, generator)
+            },
+
+            Expression::Lambda(params, body, ret_type, generator) => {
+                let (params, mut body) = try!(self.function(*params, vec!(*body), scope));
+
+                Expression::Lambda(Box::new(params), Box::new(body.remove(0)), ret_type, generator)
+            },
+
+            Expression::Match(subject, arms) => {
+                let subject = try!(self.expression(*subject, scope));
+
+                let mut result = Vec::new();
+
+                for (pattern, guard, body) in *arms {
+                    let mut inner = scope.clone();
+
+                    let pattern = self.pattern(pattern, &mut inner);
+
+                    let guard = match guard {
+                        Some(g) => Some(try!(self.expression(g, &mut inner))),
+                        None    => None,
+                    };
+
+                    result.push((pattern, guard, try!(self.expression(body, &mut inner))));
+                }
+
+                Expression::Match(Box::new(subject), Box::new(result))
+            },
+
+            e @ Expression::Integer(_)    |
+            e @ Expression::BigInteger(_) |
+            e @ Expression::Float(_)      |
+            e @ Expression::Text(_)       |
+            e @ Expression::Char(_)       |
+            e @ Expression::Bool(_)       => e,
+        })
+    }
+
+    fn pattern(&self, pattern: Pattern, scope: &mut Scope) -> Pattern {
+        match pattern {
+            Pattern::Identifier(name) => Pattern::Identifier(self.declare(name, scope)),
+
+            Pattern::Array(content) => {
+                Pattern::Array(Box::new(content.into_iter().map(|p| self.pattern(p, scope)).collect()))
+            },
+
+            Pattern::Tuple(content) => {
+                Pattern::Tuple(Box::new(content.into_iter().map(|p| self.pattern(p, scope)).collect()))
+            },
+
+            Pattern::Variant(name, content) => {
+                Pattern::Variant(name, Box::new(content.into_iter().map(|p| self.pattern(p, scope)).collect()))
+            },
+
+            Pattern::Record(name, fields) => {
+                Pattern::Record(name, Box::new(fields.into_iter().map(|(f, p)| (f, self.pattern(p, scope))).collect()))
+            },
+
+            p => p,
+        }
+    }
+}
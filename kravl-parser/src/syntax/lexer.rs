@@ -49,26 +49,27 @@ impl Lexer {
         self.top   = 0;
     }
 
-    fn push_token(&mut self, token_type: TokenType, line: &str) {
+    fn push_token(&mut self, token_type: TokenType, line: &[char]) {
         self.tokens.push(Token::new(
             token_type,
 
-            String::from(&line[self.start .. self.pos]),
+            line[self.start .. self.pos].iter().collect(),
             self.lines,
+            self.start as u32,
             self.pos as u32,
         ));
 
         self.start = self.pos;
     }
 
-    fn look(&self, line: &str, offset: usize) -> char {
-        match line.chars().nth(self.pos + offset) {
-            Some(v) => v,
+    fn look(&self, line: &[char], offset: usize) -> char {
+        match line.get(self.pos + offset) {
+            Some(v) => *v,
             None    => ' ',
         }
     }
 
-    fn skip_whitespace(&mut self, line: &str) {
+    fn skip_whitespace(&mut self, line: &[char]) {
         while self.look(line, 0) == ' ' && self.pos < line.len() - 1 {
             self.pos   += 1;
             self.start += 1;
@@ -85,28 +86,73 @@ impl Lexer {
             "~=" => Some((BinOp::NotEqual, 4)),
             "<"  => Some((BinOp::Lt, 4)),
             ">"  => Some((BinOp::Gt, 4)),
-            "<=" => Some((BinOp::GtEqual, 4)),
-            ">=" => Some((BinOp::LtEqual, 4)),
+            "<=" => Some((BinOp::LtEqual, 4)),
+            ">=" => Some((BinOp::GtEqual, 4)),
+            ">>" => Some((BinOp::Compose, 5)),
+            "|>" => Some((BinOp::Pipe, 6)),
             _    => None,
         }
     }
 
-    fn keyword(&mut self, line: &str) -> Option<TokenType> {
-        match &line[self.start .. self.pos] {
-            "define" => Some(TokenType::Definition),
-            "lambda" => Some(TokenType::Lambda),
-            "if"     => Some(TokenType::If),
-            "else"   => Some(TokenType::Else),
-            "return" => Some(TokenType::Return),
-            "true"   => Some(TokenType::True),
-            "false"  => Some(TokenType::False),
-            "do"     => Some(TokenType::Do),
-            "end"    => Some(TokenType::End),
-            _        => None
+    fn keyword(&mut self, line: &[char]) -> Option<TokenType> {
+        match &line[self.start .. self.pos].iter().collect::<String>()[..] {
+            "define"   => Some(TokenType::Definition),
+            "let"      => Some(TokenType::Let),
+            "const"    => Some(TokenType::Const),
+            "mut"      => Some(TokenType::Mut),
+            "lambda"   => Some(TokenType::Lambda),
+            "struct"   => Some(TokenType::Struct),
+            "enum"     => Some(TokenType::Enum),
+            "trait"    => Some(TokenType::Trait),
+            "impl"     => Some(TokenType::Impl),
+            "macro"    => Some(TokenType::Macro),
+            "import"   => Some(TokenType::Import),
+            "as"       => Some(TokenType::As),
+            "pub"      => Some(TokenType::Pub),
+            "if"       => Some(TokenType::If),
+            "else"     => Some(TokenType::Else),
+            "match"    => Some(TokenType::Match),
+            "while"    => Some(TokenType::While),
+            "for"      => Some(TokenType::For),
+            "in"       => Some(TokenType::In),
+            "break"    => Some(TokenType::Break),
+            "continue" => Some(TokenType::Continue),
+            "return"   => Some(TokenType::Return),
+            "try"      => Some(TokenType::Try),
+            "catch"    => Some(TokenType::Catch),
+            "throw"    => Some(TokenType::Throw),
+            "yield"    => Some(TokenType::Yield),
+            "defer"    => Some(TokenType::Defer),
+            "true"     => Some(TokenType::True),
+            "false"    => Some(TokenType::False),
+            "do"       => Some(TokenType::Do),
+            "end"      => Some(TokenType::End),
+            _          => None
         }
     }
 
-    fn is_bin_op(&mut self, line: &str) -> bool {
+    // Whether the last token is `types` and ends right where the current one starts
+    fn touches(&self, types: &[TokenType]) -> bool {
+        match self.tokens.last() {
+            Some(t) => t.row == self.lines && t.col as usize == self.start && types.contains(&t.token_type),
+            None    => false,
+        }
+    }
+
+    // Whether the last token on this line ends an operand, making a following `-` a minus
+    fn follows_operand(&self) -> bool {
+        let ends = [
+            TokenType::Identifier, TokenType::Integer, TokenType::Float, TokenType::Text, TokenType::Char,
+            TokenType::True, TokenType::False, TokenType::RParen, TokenType::RBracket, TokenType::RBrace,
+        ];
+
+        match self.tokens.last() {
+            Some(t) => t.row == self.lines && ends.contains(&t.token_type),
+            None    => false,
+        }
+    }
+
+    fn is_bin_op(&mut self, line: &[char]) -> bool {
         let mut is_bin_op = false;
         
         let mut offset = 2;
@@ -115,7 +161,7 @@ impl Lexer {
         }
 
         while offset > 0 && !is_bin_op {
-            match Lexer::bin_op(&line[self.start .. self.pos + offset]) {
+            match Lexer::bin_op(&line[self.start .. self.pos + offset].iter().collect::<String>()) {
                 Some(_) => is_bin_op = true,
                 None => ()
             }
@@ -142,6 +188,15 @@ impl Lexer {
         false
     }
 
+    pub fn get_top(&self) -> usize {
+        self.top
+    }
+
+    // Rewinds (or skips) to a position previously taken from `get_top`
+    pub fn set_top(&mut self, top: usize) {
+        self.top = top;
+    }
+
     pub fn tokens_remaining(&self) -> usize {
         self.tokens.len() - self.top
     }
@@ -166,7 +221,7 @@ impl Lexer {
         }
     }
 
-    fn push_move(&mut self, t: TokenType, line: &str) {
+    fn push_move(&mut self, t: TokenType, line: &[char]) {
         self.pos += 1;
         self.push_token(t, line);
     }
@@ -181,6 +236,10 @@ impl Lexer {
         }
 
         for line in source.lines() {
+            // positions count chars rather than bytes, so lines are lexed as chars too
+            let line: Vec<char> = line.chars().collect();
+            let line = &line[..];
+
             self.lines += 1;
             self.start  = 0;
             self.pos    = 0;
@@ -190,25 +249,62 @@ impl Lexer {
 
                 let chr  = self.look(line, 0);
 
+                // text in double quotes and a single char in single quotes, both taking escapes
                 if chr == '"' || chr == '\'' {
                     let del = chr;
 
-                    self.start += 1;
-                    self.pos   += 1;
+                    let mut content = String::new();
+
+                    self.pos += 1;
+
+                    loop {
+                        let c = match line.get(self.pos) {
+                            Some(c) => *c,
+                            None    => return Err(format!("unterminated {} on line {}", del, self.lines)),
+                        };
+
+                        if c == del {
+                            break
+                        }
+
+                        if c == '\\' {
+                            self.pos += 1;
+
+                            content.push(match self.look(line, 0) {
+                                'n'  => '\n',
+                                't'  => '\t',
+                                'r'  => '\r',
+                                '0'  => '\0',
+                                '\\' => '\\',
+                                '\'' => '\'',
+                                '"'  => '"',
+                                e    => return Err(format!("unknown escape \\{} on line {}", e, self.lines)),
+                            });
+                        } else {
+                            content.push(c);
+                        }
 
-                    while self.look(line, 0) != del {
                         self.pos += 1;
                     }
 
-                    self.push_token(TokenType::Text, line);
+                    let token_type = match del {
+                        '"' => TokenType::Text,
+                        _   => TokenType::Char,
+                    };
+
+                    if token_type == TokenType::Char && content.chars().count() != 1 {
+                        return Err(format!("char literal must hold one char: '{}' on line {}", content, self.lines))
+                    }
+
+                    self.tokens.push(Token::new(token_type, content, self.lines, self.start as u32, self.pos as u32 + 1));
 
-                    self.start += 1;
-                    self.pos   += 1;
+                    self.pos  += 1;
+                    self.start = self.pos;
 
                     continue
                 }
 
-                if chr.is_alphabetic() {
+                if chr.is_alphabetic() || chr == '_' {
                     while identifier_valid(self.look(line, 0)) {
                         self.pos += 1;
                     }
@@ -223,28 +319,96 @@ impl Lexer {
 
                 let peek = self.look(line, 1);
 
+                if chr == '.' && peek == '.' {
+                    if self.look(line, 2) == '.' {
+                        self.pos += 3;
+                        self.push_token(TokenType::Ellipsis, line);
+                    } else if self.look(line, 2) == '=' {
+                        self.pos += 3;
+                        self.push_token(TokenType::RangeInclusive, line);
+                    } else {
+                        self.pos += 2;
+                        self.push_token(TokenType::Range, line);
+                    }
+
+                    continue
+                }
+
+                // positions in `t.0.1` are plain integers rather than floats
+                if chr.is_digit(10) && self.touches(&[TokenType::Period]) {
+                    while self.look(line, 0).is_digit(10) {
+                        self.pos += 1;
+                    }
+
+                    self.push_token(TokenType::Integer, line);
+                    continue
+                }
+
+                let access = self.touches(&[TokenType::Identifier, TokenType::Integer, TokenType::RParen, TokenType::RBracket]);
+
                 if chr.is_digit(10) ||
-                   chr == '.' && peek.is_digit(10) ||
-                   chr == '-' && peek.is_digit(10) {
+                   chr == '.' && peek.is_digit(10) && !access ||
+                   chr == '-' && peek.is_digit(10) && !self.follows_operand() {
 
                     if chr == '-' {
                         self.pos += 1;
                     }
 
-                    while self.look(line, 0).is_digit(10) {
+                    let radix = match (self.look(line, 0), self.look(line, 1)) {
+                        ('0', 'x') => 16,
+                        ('0', 'o') => 8,
+                        ('0', 'b') => 2,
+                        _          => 10,
+                    };
+
+                    if radix != 10 {
+                        self.pos += 2;
+                    }
+
+                    let mut float = false;
+
+                    while self.look(line, 0).is_digit(radix) || self.look(line, 0) == '_' {
                         self.pos += 1;
                     }
 
-                    if self.look(line, 0) == '.' && self.look(line, 1).is_digit(10) {
+                    if radix == 10 && self.look(line, 0) == '.' && self.look(line, 1).is_digit(10) {
+                        float     = true;
                         self.pos += 1;
-                        while self.look(line, 0).is_digit(10) {
+
+                        while self.look(line, 0).is_digit(10) || self.look(line, 0) == '_' {
                             self.pos += 1;
                         }
-                        self.push_token(TokenType::Float, line);
-                        continue;
                     }
-                    self.push_token(TokenType::Integer, line);
-                    continue;
+
+                    // exponent, e.g. `1e9` or `2.5E-3`
+                    if radix == 10 && (self.look(line, 0) == 'e' || self.look(line, 0) == 'E') {
+                        let sign = self.look(line, 1) == '+' || self.look(line, 1) == '-';
+
+                        if self.look(line, 1).is_digit(10) || sign && self.look(line, 2).is_digit(10) {
+                            float     = true;
+                            self.pos += if sign { 2 } else { 1 };
+
+                            while self.look(line, 0).is_digit(10) || self.look(line, 0) == '_' {
+                                self.pos += 1;
+                            }
+                        }
+                    }
+
+                    // type suffix, e.g. `10i32` or `2.5f32`
+                    if self.look(line, 0).is_alphabetic() {
+                        float = float || radix == 10 && self.look(line, 0) == 'f';
+
+                        while identifier_valid(self.look(line, 0)) {
+                            self.pos += 1;
+                        }
+                    }
+
+                    match float {
+                        true  => self.push_token(TokenType::Float, line),
+                        false => self.push_token(TokenType::Integer, line),
+                    }
+
+                    continue
                 }
 
                 if chr == '-' && self.look(line, 1) == '>'  {
@@ -254,6 +418,13 @@ impl Lexer {
                     continue
                 }
 
+                if (chr == '+' || chr == '-' || chr == '*' || chr == '/') && peek == '=' {
+                    self.pos += 2;
+                    self.push_token(TokenType::CompoundAssign, line);
+
+                    continue
+                }
+
                 if self.is_bin_op(line) {
                     self.pos += 1;
                     self.push_token(TokenType::BinOp, line);
@@ -323,7 +494,7 @@ impl Lexer {
 
                     _   => {
                         panic!("fucked symbol: {}, line: {} col: {}",
-                                &line[self.start .. line.len()],
+                                line[self.start ..].iter().collect::<String>(),
                                 self.lines, self.start)
                     },
                 }
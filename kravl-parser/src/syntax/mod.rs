@@ -1,9 +1,48 @@
 pub mod tokens;
+pub mod bigint;
 pub mod lexer;
 pub mod ast;
+pub mod expand;
+pub mod resolver;
+pub mod overload;
+pub mod constant;
+pub mod prelude;
+pub mod module;
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+
+    use syntax::lexer::Lexer;
+    use syntax::ast::{Parser, Statement};
+
+    fn parse(source: &str) -> Result<Vec<Statement>, String> {
+        let mut lexer = Lexer::new();
+
+        try!(lexer.tokenize(String::from(source)));
+
+        Parser::from(lexer).parse_full()
+    }
+
+    // Writes `files` into a fresh directory named after the test
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(format!("kravl-{}-{}", test, process::id()));
+
+        let _ = fs::remove_dir_all(&root);
+
+        for (path, source) in files {
+            let path = root.join(path);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        root
+    }
+
     #[test]
     fn lexer_tokenize() {
         use syntax::lexer::Lexer;
@@ -45,4 +84,1631 @@ mod tests {
 
         let stack = parser.parse_full();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn resolver_loops() {
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            outer: for x in xs do
+                while x do
+                    break outer
+                end
+                continue
+            end
+        ").unwrap();
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+
+        let stack = parse("
+            define foo() -> int do
+                break
+            end
+        ").unwrap();
+
+        assert!(Resolver::new().resolve(&stack).is_err())
+    }
+
+    #[test]
+    fn parser_ranges() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            for i in 0..n - 1 do
+                println(i)
+            end
+
+            head = xs[0..=2]
+            word = \"kravl\"[1..3]
+        ").unwrap();
+
+        assert_eq!(stack.len(), 3);
+
+        match stack[0] {
+            Statement::For(_, _, ref iterable, _) => match **iterable {
+                Expression::Range(_, _, false) => (),
+                ref e => panic!("expected range: {:?}", e),
+            },
+            ref s => panic!("expected for loop: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Assignment(_, ref expr) => match **expr {
+                Expression::Index(_, ref index) => match **index {
+                    Expression::Range(_, _, true) => (),
+                    ref e => panic!("expected inclusive range: {:?}", e),
+                },
+                ref e => panic!("expected index: {:?}", e),
+            },
+            ref s => panic!("expected assignment: {:?}", s),
+        }
+
+        assert!(parse("let xs = [1, 2").is_err());
+        assert!(parse("xs = f(1, [2,").is_err());
+        assert!(parse("define id[T(x -> T) -> T do x end").is_err());
+        assert!(parse("let f = lambda x").is_err());
+        assert!(parse("match xs do [a, b end").is_err());
+    }
+
+    #[test]
+    fn parser_match() {
+        use syntax::ast::{Expression, Pattern};
+
+        let stack = parse("
+            describe = match xs do
+                []               -> \"empty\"
+                [x, _] if x > 10 -> \"big pair\"
+                [_, _]           -> \"pair\"
+                _                -> \"many\"
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Assignment(_, ref expr) => match **expr {
+                Expression::Match(_, ref arms) => {
+                    assert_eq!(arms.len(), 4);
+                    assert!(arms[1].1.is_some());
+
+                    match arms[3].0 {
+                        Pattern::Wildcard => (),
+                        ref p => panic!("expected wildcard: {:?}", p),
+                    }
+                },
+                ref e => panic!("expected match: {:?}", e),
+            },
+            ref s => panic!("expected assignment: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_struct() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            struct Point do
+                x -> int, y -> int
+            end
+
+            p = Point { x: 1, y: 2 }
+            p.x = p.y
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Struct(ref name, _, ref fields) => {
+                assert_eq!(name, "Point");
+                assert_eq!(fields.len(), 2);
+            },
+            ref s => panic!("expected struct: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Assignment(ref target, _) => match **target {
+                    Expression::Dot(..) => (),
+                    ref e => panic!("expected field: {:?}", e),
+                },
+                ref e => panic!("expected assignment: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_enum() {
+        use syntax::ast::{Expression, Pattern};
+        use syntax::expand::Expander;
+        use syntax::prelude::prelude;
+
+        let stack = parse("
+            enum Shape do
+                Circle(float), Rect(float, float)
+                Empty
+            end
+
+            area = match shape do
+                Circle(r)  -> r * r * 3.14
+                Rect(w, h) -> w * h
+                Empty      -> 0
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Enum(_, _, ref variants) => {
+                assert_eq!(variants.len(), 3);
+                assert_eq!(variants[1].1.len(), 2);
+            },
+            ref s => panic!("expected enum: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Assignment(_, ref expr) => match **expr {
+                Expression::Match(_, ref arms) => {
+                    match arms[1].0 {
+                        Pattern::Variant(_, ref fields) => assert_eq!(fields.len(), 2),
+                        ref p => panic!("expected variant: {:?}", p),
+                    }
+
+                    match arms[2].0 {
+                        Pattern::Variant(ref name, ref fields) => {
+                            assert_eq!(name, "Empty");
+                            assert!(fields.is_empty());
+                        },
+                        ref p => panic!("expected variant: {:?}", p),
+                    }
+                },
+                ref e => panic!("expected match: {:?}", e),
+            },
+            ref s => panic!("expected assignment: {:?}", s),
+        }
+
+        let stack = Expander::new().expand(parse("
+            macro or_zero(x) do
+                match x do
+                    Some(v) -> v
+                    None    -> 0
+                end
+            end
+
+            let n = or_zero(m)
+        ").unwrap()).unwrap();
+
+        match stack[0] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Match(_, ref arms) => match arms[1].0 {
+                    Pattern::Variant(ref name, _) => assert_eq!(name, "None"),
+                    ref p => panic!("expected variant: {:?}", p),
+                },
+                ref e => panic!("expected match: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+
+        assert_eq!(prelude().unwrap().len(), 9)
+    }
+
+    #[test]
+    fn loader_prelude() {
+        use syntax::module::Loader;
+
+        let root = write_files("prelude", &[
+            ("money.kravl", "
+                impl Add for Money do
+                    define add(self) do
+                        self
+                    end
+                end
+            "),
+        ]);
+
+        let mut loader = Loader::new(Vec::new());
+
+        let error = loader.load_entry(&root.join("money.kravl")).unwrap_err();
+
+        assert!(error.contains("`add` of Add takes 2 parameters"), "{}", error);
+        assert_eq!(loader.get_prelude().len(), 9);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn parser_import() {
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            import math
+            import util.strings as s
+
+            pub define shout(t) -> text do
+                s.upper(t)
+            end
+        ").unwrap();
+
+        match stack[1] {
+            Statement::Import(ref path, ref alias) => {
+                assert_eq!(path.len(), 2);
+                assert_eq!(alias, &Some(String::from("s")));
+            },
+            ref s => panic!("expected import: {:?}", s),
+        }
+
+        let mut resolver = Resolver::new();
+
+        resolver.import(String::from("s"), String::from("util.strings"), vec!(String::from("lower")));
+
+        assert!(resolver.resolve(&stack).is_err());
+
+        let mut resolver = Resolver::new();
+
+        resolver.import(String::from("s"), String::from("util.strings"), vec!(String::from("lower")));
+
+        assert!(resolver.resolve(&parse("
+            let s = point()
+            s.upper
+        ").unwrap()).is_ok());
+
+        assert!(Resolver::new().resolve(&parse("
+            define f() do
+                import util
+                util.g()
+            end
+        ").unwrap()).unwrap_err().contains("import util must be at the top of a module"));
+    }
+
+    #[test]
+    fn loader_modules() {
+        use syntax::module::Loader;
+
+        let root = write_files("modules", &[
+            ("main.kravl", "
+                import util.strings as s
+                import shapes
+
+                pub define shout(t) -> text do
+                    s.upper(t)
+                end
+            "),
+            ("util/strings.kravl", "
+                pub define upper(t) -> text do
+                    t
+                end
+
+                define helper(t) do
+                    t
+                end
+            "),
+            ("lib/shapes.kravl", "
+                pub struct Point do
+                    x -> int, y -> int
+                end
+            "),
+            ("private.kravl", "
+                import util.strings as s
+
+                s.helper(\"a\")
+            "),
+            ("a.kravl", "import b"),
+            ("b.kravl", "import a"),
+            ("missing.kravl", "import nowhere"),
+            ("parent.kravl", "import broken"),
+            ("broken.kravl", "let = 1"),
+        ]);
+
+        let load = |entry: &str| {
+            let mut loader = Loader::new(vec!(root.join("lib")));
+
+            loader.load_entry(&root.join(entry)).map(|_| loader)
+        };
+
+        let loader = load("main.kravl").unwrap();
+
+        let names: Vec<_> = loader.get_modules().iter().map(|m| &m.name[..]).collect();
+
+        assert_eq!(names, vec!("util.strings", "shapes", "main"));
+        assert_eq!(loader.get_modules()[0].exports, vec!(String::from("upper")));
+        assert_eq!(loader.get_modules()[1].path, root.join("lib/shapes.kravl"));
+
+        let error = |entry: &str| load(entry).err().unwrap();
+
+        assert!(error("private.kravl").contains("`helper` is not exported by util.strings"));
+        assert!(error("a.kravl").contains("import cycle: a -> b -> a"));
+        assert!(error("missing.kravl").contains("no module named nowhere"));
+        assert!(error("parent.kravl").contains(&format!("{}: ", root.join("broken.kravl").display())));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn resolver_variables() {
+        use syntax::resolver::Resolver;
+
+        let resolve = |source: &str| Resolver::new().resolve(&parse(source).unwrap());
+
+        assert!(resolve("
+            let mut total = 0
+            let limit = 10
+
+            for i in 0..limit do
+                total = total + i
+            end
+        ").is_ok());
+
+        assert!(resolve("
+            let limit = 10
+            limit = 20
+        ").is_err());
+
+        assert!(resolve("
+            define foo(a) -> int do
+                totl = a
+            end
+        ").is_err())
+    }
+
+    #[test]
+    fn parser_assignment() {
+        use syntax::ast::Expression;
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            let mut a = 1
+            let mut b = 2
+            let mut xs = [a, b]
+
+            a, b = b, a
+            [a, b] = xs
+            xs[0] += 1
+        ").unwrap();
+
+        assert_eq!(stack.len(), 6);
+
+        match stack[5] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::CompoundAssignment(ref target, _, _) => match **target {
+                    Expression::Index(..) => (),
+                    ref e => panic!("expected index: {:?}", e),
+                },
+                ref e => panic!("expected compound assignment: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+
+        match parse("t = 1, 2").unwrap()[0] {
+            Statement::Assignment(ref name, ref value) => {
+                assert_eq!(name, "t");
+
+                match **value {
+                    Expression::Tuple(ref content) => assert_eq!(content.len(), 2),
+                    ref e => panic!("expected tuple: {:?}", e),
+                }
+            },
+            ref s => panic!("expected assignment: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_bare_call() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            println \"almost now\"
+            count x - 1
+            f (a + b) c
+        ").unwrap();
+
+        assert_eq!(stack.len(), 3);
+
+        match stack[1] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ..) => match args[0] {
+                    Expression::Operation(..) => (),
+                    ref e => panic!("expected operation: {:?}", e),
+                },
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ..) => assert_eq!(args.len(), 2),
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match parse("f \"éééé\"").unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ..) => assert_eq!(args.len(), 1),
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        // indexing a text argument, like `f xs[0]`, stays inside the argument
+        match parse("f \"abc\"[0]").unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ..) => match args[..] {
+                    [Expression::Index(..)] => (),
+                    ref a => panic!("expected one index: {:?}", a),
+                },
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        // a minus right after an operand is subtraction, not a negative literal
+        match parse("x-1").unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Operation(..) => (),
+                ref e => panic!("expected operation: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match parse("f(n-1)").unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ..) => match args[0] {
+                    Expression::Operation(..) => (),
+                    ref e => panic!("expected operation: {:?}", e),
+                },
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_lambda() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            let area = lambda (w -> float, h -> float) -> float do
+                let a = w * h
+                a
+            end
+
+            let twice = define (f) do
+                lambda x: f(f(x))
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Lambda(ref params, ref body, ref ret_type, _) => {
+                    assert_eq!(params[1].kind, Some(String::from("float")));
+                    assert_eq!(*ret_type, Some(String::from("float")));
+
+                    match **body {
+                        Statement::Block(ref stack) => assert_eq!(stack.len(), 2),
+                        ref s => panic!("expected block: {:?}", s),
+                    }
+                },
+                ref e => panic!("expected lambda: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Definition(None, ..) => (),
+                ref e => panic!("expected anonymous definition: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_operators() {
+        use syntax::ast::Expression;
+        use syntax::tokens::BinOp;
+
+        let stack = parse("
+            1 - 2 * 3 - 4
+            xs |> filter(odd?) |> sum
+            inc >> double
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Operation(ref left, BinOp::Minus, ref right, _) => {
+                    match **left {
+                        Expression::Operation(_, BinOp::Minus, ..) => (),
+                        ref e => panic!("expected left associative minus: {:?}", e),
+                    }
+
+                    match **right {
+                        Expression::Integer(4) => (),
+                        ref e => panic!("expected 4: {:?}", e),
+                    }
+                },
+                ref e => panic!("expected operation: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(ref callee, ref args, ..) => {
+                    match **callee {
+                        Expression::Identifier(ref name) => assert_eq!(name, "sum"),
+                        ref e => panic!("expected sum: {:?}", e),
+                    }
+
+                    match args[0] {
+                        Expression::Call(_, ref args, ..) => assert_eq!(args.len(), 2),
+                        ref e => panic!("expected call: {:?}", e),
+                    }
+                },
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Lambda(..) => (),
+                ref e => panic!("expected lambda: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_arguments() {
+        use syntax::ast::Expression;
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            define greet(name, greeting = \"hi\", rest...) do
+                name
+            end
+
+            greet(\"a\", greeting: \"yo\")
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, ref params, ..) => {
+                    assert!(params[0].default.is_none());
+                    assert!(params[1].default.is_some());
+                    assert!(params[2].variadic);
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(_, ref args, ref named, _) => {
+                    assert_eq!(args.len(), 1);
+                    assert_eq!(named[0].0, "greeting");
+                },
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        let definition = "
+            define greet(name, greeting = \"hi\") do
+                name
+            end
+        ";
+
+        let resolve = |call: &str| Resolver::new().resolve(&parse(&format!("{}\n{}", definition, call)).unwrap());
+
+        assert!(resolve("greet(greeting: \"yo\", name: \"n\")").is_ok());
+        assert!(resolve("greet(greeting: \"yo\")").is_err());
+        assert!(resolve("greet(\"a\", \"b\", \"c\")").is_err());
+        assert!(resolve("greet(\"a\", nam: \"b\")").is_err());
+    }
+
+    #[test]
+    fn parser_collections() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            let ages = {
+                \"ann\": 31,
+                \"bob\": 27,
+            }
+            let seen = { 1, 2, 3 }
+            {}[\"a\"]
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Map(ref entries) => assert_eq!(entries.len(), 2),
+                ref e => panic!("expected map: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Set(ref members) => assert_eq!(members.len(), 3),
+                ref e => panic!("expected set: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Index(ref map, _) => match **map {
+                    Expression::Map(ref entries) => assert!(entries.is_empty()),
+                    ref e => panic!("expected map: {:?}", e),
+                },
+                ref e => panic!("expected index: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        assert!(parse("{ 1, \"a\": 2 }").is_err());
+        assert!(parse("{ \"a\": 2, 1 }").is_err());
+
+        // literals left open at the end of input
+        assert!(parse("let m = {1, 2").is_err());
+        assert!(parse("let p = Point { x: 1").is_err());
+        assert!(parse("match p do Point { x, y").is_err());
+    }
+
+    #[test]
+    fn parser_tuples() {
+        use syntax::ast::{Expression, Pattern};
+        use syntax::resolver::Resolver;
+        use syntax::tokens::TokenType;
+
+        let mut lexer = Lexer::new();
+
+        lexer.tokenize(String::from("t.0.1 (1.5)")).unwrap();
+
+        let types: Vec<_> = lexer.get_tokens().iter().map(|t| t.token_type.clone()).collect();
+
+        assert_eq!(types, vec!(
+            TokenType::Identifier, TokenType::Period, TokenType::Integer, TokenType::Period, TokenType::Integer,
+            TokenType::LParen, TokenType::Float, TokenType::RParen,
+        ));
+
+        let stack = parse("
+            define divmod(a, b) -> (int, int) do
+                return a / b, a
+            end
+
+            (1,)
+            pair.1
+            match pair do
+                (q, r) -> q
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, _, ref body, ref kind, _) => {
+                    assert_eq!(kind.as_ref().unwrap(), "(int, int)");
+
+                    match body[0] {
+                        Statement::Expression(ref expr) => match **expr {
+                            Expression::Return(Some(ref value)) => match **value {
+                                Expression::Tuple(ref content) => assert_eq!(content.len(), 2),
+                                ref e => panic!("expected tuple: {:?}", e),
+                            },
+                            ref e => panic!("expected return: {:?}", e),
+                        },
+                        ref s => panic!("expected expression: {:?}", s),
+                    }
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Tuple(ref content) => assert_eq!(content.len(), 1),
+                ref e => panic!("expected tuple: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Dot(_, ref field) => match **field {
+                    Expression::Integer(1) => (),
+                    ref e => panic!("expected position: {:?}", e),
+                },
+                ref e => panic!("expected dot: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[3] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Match(_, ref arms) => match arms[0].0 {
+                    Pattern::Tuple(ref content) => assert_eq!(content.len(), 2),
+                    ref p => panic!("expected tuple pattern: {:?}", p),
+                },
+                ref e => panic!("expected match: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        let stack = parse("
+            define divmod(a, b) do
+                return a / b, a - a / b * b
+            end
+
+            let q, r = divmod(9, 4)
+            let mut x, y = 1, 2
+            x = q + r + y
+        ").unwrap();
+
+        match stack[1] {
+            Statement::Variables(ref names, false, _) => assert_eq!(**names, vec!(String::from("q"), String::from("r"))),
+            ref s => panic!("expected variables: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Variables(_, true, ref value) => match **value {
+                Expression::Tuple(ref content) => assert_eq!(content.len(), 2),
+                ref e => panic!("expected tuple: {:?}", e),
+            },
+            ref s => panic!("expected variables: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+        assert!(Resolver::new().resolve(&parse("let a, b = 1, 2, 3").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parser_numbers() {
+        use syntax::ast::Expression;
+
+        fn number(source: &str) -> Result<Expression, String> {
+            match try!(parse(source)).remove(0) {
+                Statement::Expression(expr) => Ok(*expr),
+                s                           => panic!("expected expression: {:?}", s),
+            }
+        }
+
+        match number("0xff").unwrap() {
+            Expression::Integer(255) => (),
+            e => panic!("expected 255: {:?}", e),
+        }
+
+        match number("-0b1010_0101").unwrap() {
+            Expression::Integer(-165) => (),
+            e => panic!("expected -165: {:?}", e),
+        }
+
+        match number("2.5e3").unwrap() {
+            Expression::Float(v) => assert_eq!(v, 2500.0),
+            e => panic!("expected float: {:?}", e),
+        }
+
+        match number("10f32").unwrap() {
+            Expression::Typed(ref number, ref kind) => {
+                assert_eq!(kind, "f32");
+
+                match **number {
+                    Expression::Float(v) => assert_eq!(v, 10.0),
+                    ref e => panic!("expected float: {:?}", e),
+                }
+            },
+            e => panic!("expected typed literal: {:?}", e),
+        }
+
+        assert!(number("255u8").is_ok());
+        assert!(number("256u8").is_err());
+        assert!(number("2147483648i32").is_err());
+        assert!(number("9223372036854775808i64").is_err());
+        assert!(number("2.5i32").is_err());
+        assert!(number("12abc").is_err());
+        assert!(number("0b101f64").is_err());
+        assert!(number("0o17f32").is_err());
+        assert!(number("1_000_").is_err());
+        assert!(number("1__0").is_err());
+    }
+
+    #[test]
+    fn parser_big_integers() {
+        use syntax::ast::Expression;
+
+        fn big(source: &str) -> String {
+            match parse(source).unwrap()[0] {
+                Statement::Expression(ref expr) => match **expr {
+                    Expression::BigInteger(ref v) => v.to_string(),
+                    ref e => panic!("expected big integer: {:?}", e),
+                },
+                ref s => panic!("expected expression: {:?}", s),
+            }
+        }
+
+        assert_eq!(big("9223372036854775808"), "9223372036854775808");
+        assert_eq!(big("-280_571_172_992_510_140_037_611_932_413_038_677_189_525"),
+                   "-280571172992510140037611932413038677189525");
+        assert_eq!(big("0x1_0000_0000_0000_0000_0000_0000_0000_0000"), "340282366920938463463374607431768211456");
+    }
+
+    #[test]
+    fn lexer_chars() {
+        use syntax::ast::Expression;
+        use syntax::tokens::TokenType;
+
+        let mut lexer = Lexer::new();
+
+        lexer.tokenize(String::from(r#"'a' '\n' "say \"hi\"\t""#)).unwrap();
+
+        let tokens: Vec<_> = lexer.get_tokens().iter().map(|t| (t.token_type.clone(), t.content.clone())).collect();
+
+        assert_eq!(tokens, vec!(
+            (TokenType::Char, String::from("a")),
+            (TokenType::Char, String::from("\n")),
+            (TokenType::Text, String::from("say \"hi\"\t")),
+        ));
+
+        match Parser::from(lexer).parse_full().unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Char('a') => (),
+                ref e => panic!("expected char: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        assert!(Lexer::new().tokenize(String::from("'ab'")).is_err());
+        assert!(Lexer::new().tokenize(String::from(r#""\q""#)).is_err());
+        assert!(Lexer::new().tokenize(String::from("\"open")).is_err());
+
+        // text past a multi-byte char lexes like any other
+        match parse("let s = \"é\" + xyz").unwrap()[0] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Operation(ref left, _, ref right, _) => match (&**left, &**right) {
+                    (Expression::Text(t), Expression::Identifier(id)) => assert_eq!((&t[..], &id[..]), ("é", "xyz")),
+                    e => panic!("expected text and identifier: {:?}", e),
+                },
+                ref e => panic!("expected operation: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+
+        match parse("let b = 'é' == c").unwrap()[0] {
+            Statement::Variable(_, _, ref expr) => match **expr {
+                Expression::Operation(ref left, ..) => match **left {
+                    Expression::Char('é') => (),
+                    ref e => panic!("expected char: {:?}", e),
+                },
+                ref e => panic!("expected operation: {:?}", e),
+            },
+            ref s => panic!("expected variable: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parser_generics() {
+        use syntax::ast::Expression;
+
+        let stack = parse("
+            define map[T, U](xs -> [T], f -> (T) -> U) -> [U] do
+                xs
+            end
+
+            struct Pair[A, B] do
+                first -> A, second -> Option[B]
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, ref type_params, ref params, _, ref kind, _) => {
+                    assert_eq!(**type_params, vec!(String::from("T"), String::from("U")));
+                    assert_eq!(params[0].kind.as_ref().unwrap(), "[T]");
+                    assert_eq!(params[1].kind.as_ref().unwrap(), "(T) -> U");
+                    assert_eq!(kind.as_ref().unwrap(), "[U]");
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Struct(_, ref type_params, ref fields) => {
+                assert_eq!(type_params.len(), 2);
+                assert_eq!(fields[1].1, "Option[B]");
+            },
+            ref s => panic!("expected struct: {:?}", s),
+        }
+
+        assert!(parse("define id[T, T](x -> T) -> T do x end").is_err());
+    }
+
+    #[test]
+    fn parser_traits() {
+        use syntax::resolver::Resolver;
+
+        let show = "
+            trait Show do
+                define show(self) -> text
+                define print(self) do
+                    self.show()
+                end
+            end
+        ";
+
+        let stack = parse(&format!("{}
+            impl Show for Pair[int, int] do
+                define show(self) -> text do
+                    \"pair\"
+                end
+            end
+        ", show)).unwrap();
+
+        match stack[0] {
+            Statement::Trait(ref name, _, ref methods) => {
+                assert_eq!(name, "Show");
+                assert!(methods[0].body.is_none());
+                assert!(methods[1].body.is_some());
+            },
+            ref s => panic!("expected trait: {:?}", s),
+        }
+
+        match stack[1] {
+            Statement::Impl(Some(ref name), ref kind, ref methods) => {
+                assert_eq!(name, "Show");
+                assert_eq!(kind, "Pair[int, int]");
+                assert_eq!(methods.len(), 1);
+            },
+            ref s => panic!("expected impl: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+
+        let missing = parse(&format!("{}
+            impl Show for Point do
+                define print(self) do
+                    self
+                end
+            end
+        ", show)).unwrap();
+
+        assert!(Resolver::new().resolve(&missing).is_err());
+
+        let unknown = parse(&format!("{}
+            impl Show for Point do
+                define show(self) do
+                    self
+                end
+
+                define shout(self) do
+                    self
+                end
+            end
+        ", show)).unwrap();
+
+        assert!(Resolver::new().resolve(&unknown).is_err());
+
+        // an impl is checked even when its trait comes later
+        let before = parse(&format!("
+            impl Show for Point do
+                define shout(self) do
+                    self
+                end
+            end
+            {}
+        ", show)).unwrap();
+
+        assert!(Resolver::new().resolve(&before).unwrap_err().contains("`shout` is not a method of Show"));
+
+        assert!(Resolver::new().resolve(&parse("
+            impl Shw for Point do
+                define show(self) do
+                    self
+                end
+            end
+        ").unwrap()).unwrap_err().contains("impl of unknown trait Shw for Point"));
+    }
+
+    #[test]
+    fn resolver_overloading() {
+        use syntax::resolver::Resolver;
+        use syntax::prelude::prelude;
+        use syntax::tokens::BinOp;
+
+        fn resolve(source: &str) -> Result<(), String> {
+            let mut stack = prelude().unwrap();
+
+            stack.extend(parse(source).unwrap());
+
+            Resolver::new().resolve(&stack)
+        }
+
+        assert_eq!(BinOp::Plus.overload(), Some(("Add", "add")));
+        assert_eq!(BinOp::GtEqual.overload(), Some(("Ord", "cmp")));
+        assert_eq!(BinOp::Pipe.overload(), None);
+
+        assert!(resolve("
+            impl Add for Money do
+                define add(self, other) do
+                    Money { cents: self.cents + other.cents }
+                end
+            end
+        ").is_ok());
+
+        assert!(resolve("
+            impl Eq for Money do
+                define eq(self) -> bool do
+                    true
+                end
+            end
+        ").is_err());
+    }
+
+    #[test]
+    fn overload_operators() {
+        use syntax::ast::{Statement, Expression};
+        use syntax::overload::Overloader;
+        use syntax::tokens::BinOp;
+
+        let lower = |source: &str| Overloader::new().lower(parse(source).unwrap());
+
+        let stack = lower("
+            impl Add for Money do
+                define add(self, other) -> Money do
+                    Money { cents: self.cents + other.cents }
+                end
+            end
+
+            impl Ord for Money do
+                define cmp(self, other) -> int do
+                    self.cents - other.cents
+                end
+            end
+
+            let a = Money { cents: 1 }
+            let b = a + a + a
+            let c = a < b
+            let d = 1 + 2
+        ");
+
+        let value = |i: usize| match stack[i] {
+            Statement::Variable(_, _, ref expr) => (**expr).clone(),
+            _                                   => panic!("not a let"),
+        };
+
+        // `(a + a) + a` is `a.add(a).add(a)`, the inner call known to give a Money
+        match value(3) {
+            Expression::Call(ref callee, ref args, _, _) => match **callee {
+                Expression::Dot(ref left, ref method) => {
+                    assert_eq!(format!("{:?}", method), "Identifier(\"add\")");
+                    assert_eq!(args.len(), 1);
+
+                    match **left {
+                        Expression::Call(..) => (),
+                        ref e                => panic!("{:?}", e),
+                    }
+                },
+                ref e => panic!("{:?}", e),
+            },
+            e => panic!("{:?}", e),
+        }
+
+        match value(4) {
+            Expression::Operation(ref left, BinOp::Lt, ref right, _) => {
+                assert_eq!(format!("{:?}", right), "Integer(0)");
+
+                match **left {
+                    Expression::Call(..) => (),
+                    ref e                => panic!("{:?}", e),
+                }
+            },
+            e => panic!("{:?}", e),
+        }
+
+        match value(5) {
+            Expression::Operation(_, BinOp::Plus, _, _) => (),
+            e                                           => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn expander_macros() {
+        use syntax::ast::Expression;
+        use syntax::expand::Expander;
+        use syntax::resolver::Resolver;
+
+        let expand = |source: &str| Expander::new().expand(parse(source).unwrap());
+
+        let stack = expand("
+            macro unless(cond, body) do
+                if cond == false do
+                    body
+                end
+            end
+
+            macro swap(a, b) do
+                let tmp = a
+                a = b
+                b = tmp
+            end
+
+            let mut tmp = 1
+            let mut other = 2
+
+            unless(tmp > 1, println(tmp))
+            swap(tmp, other)
+        ").unwrap();
+
+        assert_eq!(stack.len(), 4);
+
+        match stack[3] {
+            Statement::Expansion(ref name, _, _, ref body) => {
+                assert_eq!(name, "swap");
+
+                match body[0] {
+                    Statement::Variable(ref name, _, ref value) => {
+                        assert!(name != "tmp");
+
+                        match **value {
+                            Expression::Identifier(ref n) => assert_eq!(n, "tmp"),
+                            ref e => panic!("expected identifier: {:?}", e),
+                        }
+                    },
+                    ref s => panic!("expected variable: {:?}", s),
+                }
+
+                match body[2] {
+                    Statement::Assignment(ref target, ref value) => {
+                        assert_eq!(target, "other");
+
+                        match **value {
+                            Expression::Identifier(ref n) => assert!(n != "tmp"),
+                            ref e => panic!("expected identifier: {:?}", e),
+                        }
+                    },
+                    ref s => panic!("expected assignment: {:?}", s),
+                }
+            },
+            ref s => panic!("expected expansion: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+
+        // names are resolved after expansion, still knowing the macro they came from
+        let stack = expand("
+            macro add_to(total, x) do
+                totl = total + x
+            end
+
+            let mut total = 0
+            add_to(total, 1)
+        ").unwrap();
+
+        let error = Resolver::new().resolve(&stack).unwrap_err();
+
+        assert!(error.contains("totl"));
+        assert!(error.ends_with("in expansion of macro `add_to` called at line 7, column 13, defined at line 2, column 13"));
+
+        assert!(expand("
+            macro twice(x) do
+                x
+                x
+            end
+
+            let y = twice(1)
+        ").is_err());
+
+        let error = expand("
+            macro loop(x) do
+                loop(x)
+            end
+
+            loop(1)
+        ").unwrap_err();
+
+        assert!(error.contains("in expansion of macro `loop` called at line 3, column 17, defined at line 2, column 13"));
+        assert!(error.ends_with("in expansion of macro `loop` called at line 6, column 13, defined at line 2, column 13"));
+
+        // a macro defined in a block is gone after it
+        let stack = expand("
+            define f() do
+                macro twice(x) do
+                    x + x
+                end
+
+                twice(1)
+            end
+
+            twice(2)
+        ").unwrap();
+
+        match stack[1] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Call(..) => (),
+                ref e => panic!("expected call: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn evaluator_constants() {
+        use syntax::ast::Expression;
+        use syntax::constant::Evaluator;
+        use syntax::resolver::Resolver;
+
+        fn fold(source: &str) -> Result<Vec<Statement>, String> {
+            let stack = try!(parse(source));
+
+            try!(Resolver::new().resolve(&stack));
+
+            Evaluator::new().fold(stack)
+        }
+
+        let stack = fold("
+            const define fact(n) do
+                if n <= 1 do
+                    return 1
+                end
+
+                n * fact(n - 1)
+            end
+
+            const SIZE  = fact(5) + 4
+            const NAME  = \"kravl\" + \"-\" + \"0.3\"
+            const LARGE = SIZE >= 100
+        ").unwrap();
+
+        match stack[1] {
+            Statement::Const(_, ref value) => match **value {
+                Expression::Integer(124) => (),
+                ref e => panic!("expected 124: {:?}", e),
+            },
+            ref s => panic!("expected const: {:?}", s),
+        }
+
+        match stack[2] {
+            Statement::Const(_, ref value) => match **value {
+                Expression::Text(ref t) => assert_eq!(t, "kravl-0.3"),
+                ref e => panic!("expected text: {:?}", e),
+            },
+            ref s => panic!("expected const: {:?}", s),
+        }
+
+        match stack[3] {
+            Statement::Const(_, ref value) => match **value {
+                Expression::Bool(true) => (),
+                ref e => panic!("expected true: {:?}", e),
+            },
+            ref s => panic!("expected const: {:?}", s),
+        }
+
+        assert!(fold("
+            let mut n = 1
+            const N = n
+        ").is_err());
+
+        assert!(fold("const N = 1 / 0").is_err());
+
+        let error = fold("
+            const define checked(n) do
+                if n < 0 do
+                    throw \"negative\"
+                end
+
+                n
+            end
+
+            const N = checked(-1)
+        ").unwrap_err();
+
+        assert!(error.contains("thrown at line 4, column 21"));
+
+        // locals of a const function can be reassigned, also from inside a block
+        let stack = fold("
+            const define clamp(n) do
+                let mut x = n
+                x = x + 1
+
+                if x > 10 do
+                    let n = 0
+                    x = 10 + n
+                end
+
+                x -= 1
+                x
+            end
+
+            const A = clamp(3)
+            const B = clamp(20)
+        ").unwrap();
+
+        match (&stack[1], &stack[2]) {
+            (Statement::Const(_, a), Statement::Const(_, b)) => match (&**a, &**b) {
+                (Expression::Integer(3), Expression::Integer(9)) => (),
+                e => panic!("expected 3 and 9: {:?}", e),
+            },
+            s => panic!("expected consts: {:?}", s),
+        }
+
+        // suffixed literals keep their type, and overflowing it is an error
+        match fold("const N = 100u8 + 1").unwrap()[0] {
+            Statement::Const(_, ref value) => match **value {
+                Expression::Typed(ref v, ref kind) => match **v {
+                    Expression::Integer(101) => assert_eq!(kind, "u8"),
+                    ref e => panic!("expected 101: {:?}", e),
+                },
+                ref e => panic!("expected typed: {:?}", e),
+            },
+            ref s => panic!("expected const: {:?}", s),
+        }
+
+        assert!(fold("const N = 2147483647i32 + 1i32").is_err());
+        assert!(fold("const N = 200u8 + 100u8").is_err());
+        assert!(fold("const N = 1i32 + 1i64").is_err());
+
+        // plain integers go on past an i64 as big integers, and come back when they fit one
+        let value = |source: &str| match fold(source).unwrap().remove(0) {
+            Statement::Const(_, value) => *value,
+            s                          => panic!("expected const: {:?}", s),
+        };
+
+        match value("const N = 9223372036854775807 + 1") {
+            Expression::BigInteger(ref v) => assert_eq!(v.to_string(), "9223372036854775808"),
+            ref e => panic!("expected big integer: {:?}", e),
+        }
+
+        match value("const N = 100000000000000000000 * 100000000000000000000 / 300000000000000000000 - 1") {
+            Expression::BigInteger(ref v) => assert_eq!(v.to_string(), "33333333333333333332"),
+            ref e => panic!("expected big integer: {:?}", e),
+        }
+
+        match value("const N = -100000000000000000000 + 99999999999999999999") {
+            Expression::Integer(-1) => (),
+            ref e => panic!("expected -1: {:?}", e),
+        }
+
+        match value("const N = 100000000000000000000 > 9223372036854775807") {
+            Expression::Bool(true) => (),
+            ref e => panic!("expected true: {:?}", e),
+        }
+
+        assert!(fold("
+            define foo() do
+                const N = 1
+            end
+        ").is_err());
+    }
+
+    #[test]
+    fn parser_try() {
+        use syntax::ast::Expression;
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            try do
+                throw \"oops\"
+            end catch e do
+                println(e)
+            end
+            done()
+        ").unwrap();
+
+        assert_eq!(stack.len(), 2);
+
+        match stack[0] {
+            Statement::Try(ref body, ref name, _, position) => {
+                assert_eq!(position, (2, 13));
+
+                assert_eq!(name.as_ref().unwrap(), "e");
+
+                match **body {
+                    Statement::Block(ref stack) => match stack[0] {
+                        Statement::Expression(ref expr) => match **expr {
+                            Expression::Throw(_, position) => assert_eq!(position, (3, 17)),
+                            ref e => panic!("expected throw: {:?}", e),
+                        },
+                        ref s => panic!("expected expression: {:?}", s),
+                    },
+                    ref s => panic!("expected block: {:?}", s),
+                }
+            },
+            ref s => panic!("expected try: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+
+        assert!(Resolver::new().resolve(&parse("
+            try do
+                risky()
+            end catch e do
+                e = 1
+            end
+        ").unwrap()).is_err());
+
+        // nothing can catch a throw at the top of a module
+        let error = Resolver::new().resolve(&parse("throw \"oops\"").unwrap()).unwrap_err();
+
+        assert_eq!(error, "`throw` at line 1, column 1 is outside of a function or `try`");
+    }
+
+    #[test]
+    fn parser_defer() {
+        use syntax::ast::Expression;
+        use syntax::resolver::Resolver;
+
+        let stack = parse("
+            define copy(from, to) do
+                let f = open(from)
+                defer close(f)
+                defer do
+                    println(\"copied\")
+                end
+
+                write(to, read(f))
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, _, ref body, _, _) => {
+                    assert_eq!(body.len(), 4);
+
+                    match body[1] {
+                        Statement::Defer(ref deferred) => match **deferred {
+                            Statement::Expression(_) => (),
+                            ref s => panic!("expected expression: {:?}", s),
+                        },
+                        ref s => panic!("expected defer: {:?}", s),
+                    }
+
+                    match body[2] {
+                        Statement::Defer(ref deferred) => match **deferred {
+                            Statement::Block(_) => (),
+                            ref s => panic!("expected block: {:?}", s),
+                        },
+                        ref s => panic!("expected defer: {:?}", s),
+                    }
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+        assert!(Resolver::new().resolve(&parse("defer close(f)").unwrap()).is_err());
+
+        assert!(Resolver::new().resolve(&parse("
+            while true do
+                defer do
+                    break
+                end
+            end
+        ").unwrap()).is_err());
+
+        let error = Resolver::new().resolve(&parse("define f() do defer do return 1 end 2 end").unwrap()).unwrap_err();
+
+        assert_eq!(error, "`return` inside `defer`");
+
+        let error = Resolver::new().resolve(&parse("define f() do defer do yield 1 end end").unwrap()).unwrap_err();
+
+        assert_eq!(error, "`yield` inside `defer`");
+
+        // a function defined in deferred code returns from itself
+        assert!(Resolver::new().resolve(&parse("
+            define f() do
+                defer do
+                    let g = lambda x do
+                        return x
+                    end
+                end
+            end
+        ").unwrap()).is_ok());
+    }
+
+    #[test]
+    fn parser_generators() {
+        use syntax::ast::Expression;
+        use syntax::resolver::Resolver;
+        use syntax::prelude::prelude;
+
+        let stack = parse("
+            define fib() do
+                let mut a = 0
+                let mut b = 1
+
+                while true do
+                    yield a
+                    a, b = b, a + b
+                end
+            end
+
+            for n in fib() do
+                println(n)
+            end
+        ").unwrap();
+
+        match stack[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, _, ref body, _, generator) => match body[2] {
+                    _ if !generator => panic!("expected a generator"),
+
+                    Statement::While(_, _, ref body) => match **body {
+                        Statement::Block(ref body) => match body[0] {
+                            Statement::Expression(ref expr) => match **expr {
+                                Expression::Yield(ref value) => match **value {
+                                    Expression::Identifier(ref name) => assert_eq!(name, "a"),
+                                    ref e => panic!("expected identifier: {:?}", e),
+                                },
+                                ref e => panic!("expected yield: {:?}", e),
+                            },
+                            ref s => panic!("expected expression: {:?}", s),
+                        },
+                        ref s => panic!("expected block: {:?}", s),
+                    },
+                    ref s => panic!("expected while: {:?}", s),
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+
+        assert!(Resolver::new().resolve(&stack).is_ok());
+        assert!(Resolver::new().resolve(&parse("yield 1").unwrap()).is_err());
+        assert!(Resolver::new().resolve(&parse("let double = lambda n: yield n * 2").unwrap()).is_ok());
+
+        // only the function the `yield` is directly in is a generator
+        let generators = |source: &str| match parse(source).unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, _, ref body, _, generator) => match body[0] {
+                    Statement::Variable(_, _, ref value) => match **value {
+                        Expression::Lambda(_, _, _, inner) => (generator, inner),
+                        ref e => panic!("expected lambda: {:?}", e),
+                    },
+                    ref s => panic!("expected variable: {:?}", s),
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        };
+
+        assert_eq!(generators("define f() do\n    let g = lambda n: yield n\n    g\nend"), (false, true));
+        assert_eq!(generators("define f() do\n    let g = lambda n: n\n    yield g\nend"), (true, false));
+
+        // the prelude's Iterator is generic over what it yields
+        let mut prelude = prelude().unwrap();
+
+        prelude.extend(parse("
+            impl Iterator[int] for Counter do
+                define next(self) -> Option[int] do
+                    None
+                end
+            end
+        ").unwrap());
+
+        assert!(Resolver::new().resolve(&prelude).is_ok());
+
+        // a keyword with nothing after it on its line
+        assert!(parse("yield").is_err());
+        assert!(parse("throw").is_err());
+        assert!(parse("define f() do\n    yield\n    1\nend").is_err());
+
+        // except `return`, which then returns unit
+        match parse("define f(x) do\n    if x do return end\n    return\n    1\nend").unwrap()[0] {
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(_, _, _, ref body, _, _) => {
+                    assert_eq!(body.len(), 3);
+
+                    match body[1] {
+                        Statement::Expression(ref expr) => match **expr {
+                            Expression::Return(None) => (),
+                            ref e => panic!("expected bare return: {:?}", e),
+                        },
+                        ref s => panic!("expected expression: {:?}", s),
+                    }
+                },
+                ref e => panic!("expected definition: {:?}", e),
+            },
+            ref s => panic!("expected expression: {:?}", s),
+        }
+    }
+}
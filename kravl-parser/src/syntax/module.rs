@@ -0,0 +1,206 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use syntax::lexer::Lexer;
+use syntax::expand::Expander;
+use syntax::resolver::Resolver;
+use syntax::overload::Overloader;
+use syntax::constant::Evaluator;
+use syntax::prelude::prelude;
+use syntax::ast::{
+    Parser,
+    Statement,
+    Expression,
+};
+
+/* Turns an entry file and everything it imports into modules.
+ * `import util.strings` is looked up as `util/strings.kravl`,
+ * first next to the entry file and then along the search path.
+ * Every module is resolved knowing the declarations of the prelude.
+ */
+
+pub struct Module {
+    pub name:       String,
+    pub path:       PathBuf,
+    pub statements: Vec<Statement>,
+    pub exports:    Vec<String>,
+}
+
+pub struct Loader {
+    root:        PathBuf,
+    search_path: Vec<PathBuf>,
+    prelude:     Vec<Statement>,
+    modules:     Vec<Module>,
+    loading:     Vec<String>,
+}
+
+impl Loader {
+    pub fn new(search_path: Vec<PathBuf>) -> Loader {
+        Loader {
+            root:        PathBuf::new(),
+            search_path: search_path,
+            prelude:     Vec::new(),
+            modules:     Vec::new(),
+            loading:     Vec::new(),
+        }
+    }
+
+    // Loaded modules, every module after the ones it imports
+    pub fn get_modules(&self) -> &Vec<Module> {
+        &self.modules
+    }
+
+    // Declarations every module starts out with, such as Option and the operator traits
+    pub fn get_prelude(&self) -> &Vec<Statement> {
+        &self.prelude
+    }
+
+    pub fn load_entry(&mut self, path: &Path) -> Result<(), String> {
+        self.root = match path.parent() {
+            Some(p) => p.to_path_buf(),
+            None    => PathBuf::new(),
+        };
+
+        let name = match path.file_stem() {
+            Some(s) => s.to_string_lossy().into_owned(),
+            None    => return Err(format!("not a file: {}", path.display())),
+        };
+
+        if self.prelude.is_empty() {
+            self.prelude = try!(prelude());
+        }
+
+        self.load(name, path.to_path_buf())
+    }
+
+    fn load(&mut self, name: String, path: PathBuf) -> Result<(), String> {
+        if self.loading.contains(&name) {
+            return Err(format!("import cycle: {} -> {}", self.loading.join(" -> "), name))
+        }
+
+        if self.modules.iter().any(|m| m.name == name) {
+            return Ok(())
+        }
+
+        let mut source = match File::open(&path) {
+            Ok(f)  => f,
+            Err(_) => return Err(format!("failed to open: {}", path.display())),
+        };
+
+        let mut buffer = String::new();
+
+        if source.read_to_string(&mut buffer).is_err() {
+            return Err(format!("failed to read: {}", path.display()))
+        }
+
+        let mut lexer = Lexer::new();
+
+        if let Err(e) = lexer.tokenize(buffer) {
+            return Err(format!("{}: {}", path.display(), e))
+        }
+
+        let statements = match Parser::from(lexer).parse_full() {
+            Ok(s)  => s,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let statements = match Expander::new().expand(statements) {
+            Ok(s)  => s,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        self.loading.push(name.clone());
+
+        let mut resolver = Resolver::new();
+
+        try!(resolver.resolve(&self.prelude));
+
+        for s in &statements {
+            if let Statement::Import(ref import, ref alias) = *s {
+                let import_name = import.join(".");
+                let import_path = try!(self.locate(import));
+
+                try!(self.load(import_name.clone(), import_path));
+
+                let exports = self.modules.iter()
+                                          .find(|m| m.name == import_name)
+                                          .map(|m| m.exports.clone())
+                                          .unwrap_or_default();
+
+                let alias = match *alias {
+                    Some(ref a) => a.clone(),
+                    None        => import[import.len() - 1].clone(),
+                };
+
+                resolver.import(alias, import_name, exports);
+            }
+        }
+
+        self.loading.pop();
+
+        if let Err(e) = resolver.resolve(&statements) {
+            return Err(format!("{}: {}", path.display(), e))
+        }
+
+        let statements = Overloader::new().lower(statements);
+
+        let statements = match Evaluator::new().fold(statements) {
+            Ok(s)  => s,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let exports = statements.iter().filter_map(Loader::exported).collect();
+
+        self.modules.push(Module {
+            name:       name,
+            path:       path,
+            statements: statements,
+            exports:    exports,
+        });
+
+        Ok(())
+    }
+
+    fn locate(&self, import: &[String]) -> Result<PathBuf, String> {
+        let mut relative = import.iter().collect::<PathBuf>();
+        relative.set_extension("kravl");
+
+        let roots = Some(&self.root).into_iter().chain(self.search_path.iter());
+
+        for root in roots {
+            let path = root.join(&relative);
+
+            if path.is_file() {
+                return Ok(path)
+            }
+        }
+
+        Err(format!("no module named {}", import.join(".")))
+    }
+
+    fn exported(statement: &Statement) -> Option<String> {
+        let statement = match *statement {
+            Statement::Public(ref s) => s,
+            _                        => return None,
+        };
+
+        match **statement {
+            Statement::Struct(ref name, ..)     |
+            Statement::Enum(ref name, ..)       |
+            Statement::Trait(ref name, ..)      |
+            Statement::Const(ref name, _)       |
+            Statement::Variable(ref name, _, _) => Some(name.clone()),
+
+            Statement::Expression(ref expr) => match **expr {
+                Expression::Definition(ref name, ..) => name.clone(),
+                _                                    => None,
+            },
+
+            _ => None,
+        }
+    }
+}
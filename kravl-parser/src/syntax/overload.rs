@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use syntax::tokens::BinOp;
+use syntax::ast::{
+    Statement,
+    Expression,
+    Pattern,
+    Parameter,
+    Position,
+};
+
+/* Lowers operators on user types to the methods of their prelude traits,
+ * e.g. `a + b` to `a.add(b)` when `a` is a `Money` with an `impl Add for Money`.
+ * The left operand's type is known from record literals, typed parameters,
+ * `self` inside an impl and the declared return type of a lowered method.
+ * Anything else keeps the built in operator.
+ */
+
+pub struct Overloader {
+    // trait and method names implemented for each type, with the method's return type
+    impls:  HashMap<(String, String), Option<String>>,
+    scopes: Vec<HashMap<String, Option<String>>>,
+}
+
+impl Overloader {
+    pub fn new() -> Overloader {
+        Overloader {
+            impls:  HashMap::new(),
+            scopes: vec!(HashMap::new()),
+        }
+    }
+
+    pub fn lower(&mut self, stack: Vec<Statement>) -> Vec<Statement> {
+        for s in &stack {
+            let s = match *s {
+                Statement::Public(ref s) => s,
+                ref s                    => s,
+            };
+
+            if let Statement::Impl(Some(ref name), ref kind, ref methods) = *s {
+                for m in methods.iter() {
+                    if let Statement::Expression(ref expr) = *m {
+                        if let Expression::Definition(Some(ref method), _, _, _, ref ret_type, _) = **expr {
+                            self.impls.insert((kind.clone(), method.clone()), ret_type.clone());
+                        }
+                    }
+                }
+
+                self.impls.insert((kind.clone(), name.clone()), None);
+            }
+        }
+
+        self.statements(stack)
+    }
+
+    fn declare(&mut self, name: &str, kind: Option<String>) {
+        self.scopes.last_mut().unwrap().insert(String::from(name), kind);
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern) {
+        match *pattern {
+            Pattern::Identifier(ref name) => self.declare(name, None),
+
+            Pattern::Array(ref content)      |
+            Pattern::Tuple(ref content)      |
+            Pattern::Variant(_, ref content) => {
+                for p in content.iter() {
+                    self.declare_pattern(p);
+                }
+            },
+
+            Pattern::Record(_, ref fields) => {
+                for (_, p) in fields.iter() {
+                    self.declare_pattern(p);
+                }
+            },
+
+            _ => (),
+        }
+    }
+
+    // Type of `expression` if it is known to be a user type
+    fn kind(&self, expression: &Expression) -> Option<String> {
+        match *expression {
+            Expression::Record(ref name, _) => Some(name.clone()),
+
+            Expression::Identifier(ref name) => {
+                self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next().cloned().unwrap_or(None)
+            },
+
+            Expression::Call(ref callee, ..) => match **callee {
+                Expression::Dot(ref left, ref method) => match (self.kind(left), &**method) {
+                    (Some(kind), Expression::Identifier(method)) => {
+                        self.impls.get(&(kind, method.clone())).cloned().unwrap_or(None)
+                    },
+                    _ => None,
+                },
+                _ => None,
+            },
+
+            _ => None,
+        }
+    }
+
+    // `left.method(right)`
+    fn method_call(left: Expression, method: &str, right: Expression, position: Position) -> Expression {
+        Expression::Call(
+            Box::new(Expression::Dot(Box::new(left), Box::new(Expression::Identifier(String::from(method))))),
+            Box::new(vec!(right)),
+            Box::default(),
+            position,
+        )
+    }
+
+    // Trait and method of `op` if `left` is known to implement it
+    fn method(&self, op: &BinOp, left: &Expression) -> Option<(String, String)> {
+        match (op.overload(), self.kind(left)) {
+            (Some((name, method)), Some(kind)) => match self.impls.contains_key(&(kind, String::from(name))) {
+                true  => Some((String::from(name), String::from(method))),
+                false => None,
+            },
+            _ => None,
+        }
+    }
+
+    // The call an operator stands for when `left` has an impl of its trait
+    fn overload(&self, left: Expression, op: BinOp, right: Expression, position: Position) -> Expression {
+        let (name, method) = match self.method(&op, &left) {
+            Some(m) => m,
+            None    => return Expression::Operation(Box::new(left), op, Box::new(right), position),
+        };
+
+        let call = Overloader::method_call(left, &method, right, position);
+
+        match (&*name, op) {
+            ("Eq", BinOp::NotEqual) => Expression::Operation(Box::new(call), BinOp::Equal, Box::new(Expression::Bool(false)), position),
+            ("Ord", op)             => Expression::Operation(Box::new(call), op, Box::new(Expression::Integer(0)), position),
+            _                       => call,
+        }
+    }
+
+    fn function(&mut self, params: Vec<Parameter>, body: Vec<Statement>, receiver: Option<&str>) -> (Vec<Parameter>, Vec<Statement>) {
+        let params: Vec<Parameter> = params.into_iter().map(|mut p| {
+            p.default = p.default.map(|d| self.expression(d));
+            p
+        }).collect();
+
+        self.scopes.push(HashMap::new());
+
+        for p in &params {
+            let kind = match receiver {
+                Some(kind) if p.name == "self" => Some(String::from(kind)),
+                _                              => p.kind.clone(),
+            };
+
+            self.declare(&p.name, kind);
+        }
+
+        let body = self.statements(body);
+
+        self.scopes.pop();
+
+        (params, body)
+    }
+
+    fn statements(&mut self, stack: Vec<Statement>) -> Vec<Statement> {
+        stack.into_iter().map(|s| self.statement(s)).collect()
+    }
+
+    fn statement(&mut self, statement: Statement) -> Statement {
+        match statement {
+            Statement::If(condition, body) => {
+                let condition = self.expression(*condition);
+
+                Statement::If(Box::new(condition), Box::new(self.statement(*body)))
+            },
+
+            Statement::IfElse(condition, body, else_body) => {
+                let condition = self.expression(*condition);
+                let body      = self.statement(*body);
+
+                Statement::IfElse(Box::new(condition), Box::new(body), Box::new(self.statement(*else_body)))
+            },
+
+            Statement::Try(body, name, handler, position) => {
+                let body = self.statement(*body);
+
+                self.scopes.push(HashMap::new());
+
+                if let Some(ref name) = name {
+                    self.declare(name, None);
+                }
+
+                let handler = self.statement(*handler);
+
+                self.scopes.pop();
+
+                Statement::Try(Box::new(body), name, Box::new(handler), position)
+            },
+
+            Statement::Defer(body) => Statement::Defer(Box::new(self.statement(*body))),
+
+            Statement::While(label, condition, body) => {
+                let condition = self.expression(*condition);
+
+                Statement::While(label, Box::new(condition), Box::new(self.statement(*body)))
+            },
+
+            Statement::For(label, name, iterable, body) => {
+                let iterable = self.expression(*iterable);
+
+                self.scopes.push(HashMap::new());
+                self.declare(&name, None);
+
+                let body = self.statement(*body);
+
+                self.scopes.pop();
+
+                Statement::For(label, name, Box::new(iterable), Box::new(body))
+            },
+
+            Statement::Variable(name, mutable, expr) => {
+                let expr = self.expression(*expr);
+                let kind = self.kind(&expr);
+
+                self.declare(&name, kind);
+
+                Statement::Variable(name, mutable, Box::new(expr))
+            },
+
+            Statement::Variables(names, mutable, expr) => {
+                let expr = self.expression(*expr);
+
+                for name in names.iter() {
+                    self.declare(name, None);
+                }
+
+                Statement::Variables(names, mutable, Box::new(expr))
+            },
+
+            Statement::Const(name, expr) => {
+                let expr = self.expression(*expr);
+                let kind = self.kind(&expr);
+
+                self.declare(&name, kind);
+
+                Statement::Const(name, Box::new(expr))
+            },
+
+            Statement::Block(stack) => {
+                self.scopes.push(HashMap::new());
+                let stack = self.statements(*stack);
+                self.scopes.pop();
+
+                Statement::Block(Box::new(stack))
+            },
+
+            Statement::Expansion(name, call, defined, stack) => {
+                self.scopes.push(HashMap::new());
+                let stack = self.statements(*stack);
+                self.scopes.pop();
+
+                Statement::Expansion(name, call, defined, Box::new(stack))
+            },
+
+            Statement::Expression(expr) => Statement::Expression(Box::new(self.expression(*expr))),
+
+            Statement::Assignment(name, expr) => Statement::Assignment(name, Box::new(self.expression(*expr))),
+
+            Statement::Trait(name, type_params, methods) => {
+                let methods = methods.into_iter().map(|mut m| {
+                    if let Some(body) = m.body {
+                        let (params, body) = self.function(m.params, body, None);
+
+                        m.params = params;
+                        m.body   = Some(body);
+                    }
+
+                    m
+                }).collect();
+
+                Statement::Trait(name, type_params, Box::new(methods))
+            },
+
+            Statement::Impl(name, kind, methods) => {
+                let methods = methods.into_iter().map(|s| match s {
+                    Statement::Expression(expr) => match *expr {
+                        Expression::Definition(method, type_params, params, body, ret_type, generator) => {
+                            let (params, body) = self.function(*params, *body, Some(&kind));
+
+                            Statement::Expression(Box::new(Expression::Definition(
+                                method, type_params, Box::new(params), Box::new(body), ret_type, generator
+                            )))
+                        },
+
+                        expr => Statement::Expression(Box::new(expr)),
+                    },
+
+                    s => s,
+                }).collect();
+
+                Statement::Impl(name, kind, Box::new(methods))
+            },
+
+            Statement::Public(statement) => Statement::Public(Box::new(self.statement(*statement))),
+
+            s @ Statement::Break(_)    |
+            s @ Statement::Continue(_) |
+            s @ Statement::Struct(..)  |
+            s @ Statement::Enum(..)    |
+            s @ Statement::Macro(..)   |
+            s @ Statement::Import(..)  => s,
+        }
+    }
+
+    fn expressions(&mut self, stack: Vec<Expression>) -> Vec<Expression> {
+        stack.into_iter().map(|e| self.expression(e)).collect()
+    }
+
+    fn expression(&mut self, expression: Expression) -> Expression {
+        match expression {
+            Expression::Operation(left, op, right, position) => {
+                let left  = self.expression(*left);
+                let right = self.expression(*right);
+
+                self.overload(left, op, right, position)
+            },
+
+            Expression::CompoundAssignment(target, op, expr) => {
+                let target = self.expression(*target);
+
+                Expression::CompoundAssignment(Box::new(target), op, Box::new(self.expression(*expr)))
+            },
+
+            Expression::Call(callee, args, named, position) => {
+                let callee = self.expression(*callee);
+                let args   = self.expressions(*args);
+                let named  = named.into_iter().map(|(n, a)| (n, self.expression(a))).collect();
+
+                Expression::Call(Box::new(callee), Box::new(args), Box::new(named), position)
+            },
+
+            Expression::Assignment(target, expr) => {
+                let target = self.expression(*target);
+
+                Expression::Assignment(Box::new(target), Box::new(self.expression(*expr)))
+            },
+
+            Expression::Dot(left, field) => Expression::Dot(Box::new(self.expression(*left)), field),
+
+            Expression::Index(left, index) => {
+                let left = self.expression(*left);
+
+                Expression::Index(Box::new(left), Box::new(self.expression(*index)))
+            },
+
+            Expression::Range(start, end, inclusive) => {
+                let start = self.expression(*start);
+
+                Expression::Range(Box::new(start), Box::new(self.expression(*end)), inclusive)
+            },
+
+            Expression::Array(content) => Expression::Array(Box::new(self.expressions(*content))),
+            Expression::Tuple(content) => Expression::Tuple(Box::new(self.expressions(*content))),
+            Expression::Set(content)   => Expression::Set(Box::new(self.expressions(*content))),
+
+            Expression::Map(entries) => {
+                let entries = entries.into_iter().map(|(k, v)| (self.expression(k), self.expression(v))).collect();
+
+                Expression::Map(Box::new(entries))
+            },
+
+            Expression::Record(name, fields) => {
+                let fields = fields.into_iter().map(|(f, e)| (f, self.expression(e))).collect();
+
+                Expression::Record(name, Box::new(fields))
+            },
+
+            Expression::Typed(expr, kind)     => Expression::Typed(Box::new(self.expression(*expr)), kind),
+            Expression::Return(expr)          => Expression::Return(expr.map(|e| Box::new(self.expression(*e)))),
+            Expression::Throw(expr, position) => Expression::Throw(Box::new(self.expression(*expr)), position),
+            Expression::Yield(expr)           => Expression::Yield(Box::new(self.expression(*expr))),
+
+            Expression::Definition(name, type_params, params, body, ret_type, generator) => {
+                if let Some(ref n) = name {
+                    self.declare(n, None);
+                }
+
+                let (params, body) = self.function(*params, *body, None);
+
+                Expression::Definition(name, type_params, Box::new(params), Box::new(body), ret_type, generator)
+            },
+
+            Expression::Lambda(params, body, ret_type, generator) => {
+                let (params, mut body) = self.function(*params, vec!(*body), None);
+
+                Expression::Lambda(Box::new(params), Box::new(body.remove(0)), ret_type, generator)
+            },
+
+            Expression::Match(subject, arms) => {
+                let subject = self.expression(*subject);
+
+                let arms = arms.into_iter().map(|(pattern, guard, body)| {
+                    self.scopes.push(HashMap::new());
+                    self.declare_pattern(&pattern);
+
+                    let guard = guard.map(|g| self.expression(g));
+                    let body  = self.expression(body);
+
+                    self.scopes.pop();
+
+                    (pattern, guard, body)
+                }).collect();
+
+                Expression::Match(Box::new(subject), Box::new(arms))
+            },
+
+            e @ Expression::Integer(_)    |
+            e @ Expression::BigInteger(_) |
+            e @ Expression::Float(_)      |
+            e @ Expression::Text(_)       |
+            e @ Expression::Char(_)       |
+            e @ Expression::Bool(_)       |
+            e @ Expression::Identifier(_) => e,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use syntax::lexer::Lexer;
+use syntax::ast::{
+    Parser,
+    Statement,
+};
+
+/* Declarations every program starts out with,
+ * written in kravl itself.
+ */
+
+pub const SOURCE: &str = "
+enum Option[T] do
+    Some(T), None
+end
+
+enum Result[T, E] do
+    Ok(T), Err(E)
+end
+
+trait Add do define add(self, other) end
+trait Sub do define sub(self, other) end
+trait Mul do define mul(self, other) end
+trait Div do define div(self, other) end
+trait Eq  do define eq(self, other) -> bool end
+trait Ord do define cmp(self, other) -> int end
+
+trait Iterator[T] do define next(self) -> Option[T] end
+";
+
+pub fn prelude() -> Result<Vec<Statement>, String> {
+    let mut lexer = Lexer::new();
+
+    try!(lexer.tokenize(String::from(SOURCE)));
+
+    Parser::from(lexer).parse_full()
+}
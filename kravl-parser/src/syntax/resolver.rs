@@ -0,0 +1,613 @@
+use std::collections::HashMap;
+
+use syntax::expand::in_expansion;
+use syntax::ast::{
+    Statement,
+    Expression,
+    Pattern,
+    Parameter,
+    Method,
+};
+
+/* Walks a parsed program before it is handed on,
+ * rejecting things the parser alone can't see.
+ */
+
+struct Binding {
+    mutable: bool,
+    params:  Option<Vec<Parameter>>,
+}
+
+pub struct Resolver {
+    loops:    Vec<Option<String>>,
+    imports:  HashMap<String, (String, Vec<String>)>,
+    scopes:   Vec<HashMap<String, Binding>>,
+    traits:   HashMap<String, Vec<Method>>,
+    depth:    usize,
+    tries:    usize,
+    deferred: bool,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            loops:    Vec::new(),
+            imports:  HashMap::new(),
+            scopes:   vec!(HashMap::new()),
+            traits:   HashMap::new(),
+            depth:    0,
+            tries:    0,
+            deferred: false,
+        }
+    }
+
+    // Makes the exports of `module` reachable through `alias.name`
+    pub fn import(&mut self, alias: String, module: String, exports: Vec<String>) {
+        self.imports.insert(alias, (module, exports));
+    }
+
+    pub fn resolve(&mut self, stack: &[Statement]) -> Result<(), String> {
+        // traits are known to the whole block, so an impl may come before its trait
+        for s in stack {
+            let s = match *s {
+                Statement::Public(ref s) => s,
+                ref s                    => s,
+            };
+
+            if let Statement::Trait(ref name, _, ref methods) = *s {
+                self.traits.insert(name.clone(), methods.to_vec());
+            }
+        }
+
+        for s in stack {
+            try!(self.resolve_statement(s));
+        }
+
+        Ok(())
+    }
+
+    // Declares `name` in the innermost scope, `mutable` telling whether it may be reassigned
+    fn declare(&mut self, name: &str, mutable: bool) {
+        self.scopes.last_mut().unwrap().insert(String::from(name), Binding {
+            mutable: mutable,
+            params:  None,
+        });
+    }
+
+    // Declares an immutable `name` known to be a function taking `params`
+    fn declare_function(&mut self, name: &str, params: &[Parameter]) {
+        self.scopes.last_mut().unwrap().insert(String::from(name), Binding {
+            mutable: false,
+            params:  Some(params.to_vec()),
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next()
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern) {
+        match *pattern {
+            Pattern::Identifier(ref name) => self.declare(name, false),
+
+            Pattern::Array(ref content)      |
+            Pattern::Tuple(ref content)      |
+            Pattern::Variant(_, ref content) => {
+                for p in content.iter() {
+                    self.declare_pattern(p);
+                }
+            },
+
+            Pattern::Record(_, ref fields) => {
+                for (_, p) in fields.iter() {
+                    self.declare_pattern(p);
+                }
+            },
+
+            _ => (),
+        }
+    }
+
+    fn resolve_assignment(&self, name: &str) -> Result<(), String> {
+        match self.lookup(name) {
+            Some(binding) if binding.mutable => Ok(()),
+            Some(_) => Err(format!("assignment to immutable variable: {}", name)),
+            None    => Err(format!("assignment to undeclared variable: {}", name)),
+        }
+    }
+
+    fn resolve_arity(&self, name: &str, args: usize, named: &[(String, Expression)]) -> Result<(), String> {
+        let params = match self.lookup(name) {
+            Some(&Binding { params: Some(ref params), .. }) => params,
+            _                                               => return Ok(()),
+        };
+
+        let variadic = params.last().is_some_and(|p| p.variadic);
+        let fixed    = if variadic { &params[.. params.len() - 1] } else { &params[..] };
+
+        if args > fixed.len() && !variadic {
+            return Err(format!(
+                "too many arguments to {}: expected at most {} but found {}", name, fixed.len(), args
+            ))
+        }
+
+        for (i, (arg, _)) in named.iter().enumerate() {
+            match fixed.iter().position(|p| p.name == *arg) {
+                Some(p) if p < args => {
+                    return Err(format!("argument `{}` to {} given twice", arg, name))
+                },
+
+                Some(_) => if named[.. i].iter().any(|(n, _)| n == arg) {
+                    return Err(format!("argument `{}` to {} given twice", arg, name))
+                },
+
+                None => return Err(format!("{} has no parameter `{}`", name, arg)),
+            }
+        }
+
+        for (i, p) in fixed.iter().enumerate() {
+            if i >= args && p.default.is_none() && !named.iter().any(|(n, _)| *n == p.name) {
+                return Err(format!("missing argument `{}` to {}", p.name, name))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_target(&mut self, target: &Expression) -> Result<(), String> {
+        match *target {
+            Expression::Identifier(ref name) => self.resolve_assignment(name),
+
+            Expression::Array(ref content) |
+            Expression::Tuple(ref content) => {
+                for t in content.iter() {
+                    try!(self.resolve_target(t));
+                }
+
+                Ok(())
+            },
+
+            _ => {
+                let mut root = target;
+
+                while let Expression::Dot(ref left, _) | Expression::Index(ref left, _) = *root {
+                    root = left;
+                }
+
+                if let Expression::Identifier(ref name) = *root {
+                    try!(self.resolve_assignment(name));
+                }
+
+                self.resolve_expression(target)
+            },
+        }
+    }
+
+    // Resolves `body` in a fresh scope holding `params`, outside of any loop
+    fn resolve_function(&mut self, params: &[Parameter], body: &[Statement]) -> Result<(), String> {
+        for p in params {
+            if let Some(ref default) = p.default {
+                try!(self.resolve_expression(default));
+            }
+        }
+
+        let loops    = ::std::mem::take(&mut self.loops);
+        let deferred = ::std::mem::replace(&mut self.deferred, false);
+
+        self.scopes.push(HashMap::new());
+
+        for p in params {
+            self.declare(&p.name, false);
+        }
+
+        self.depth += 1;
+        let result = self.resolve(body);
+        self.depth -= 1;
+
+        self.scopes.pop();
+        self.loops    = loops;
+        self.deferred = deferred;
+
+        result
+    }
+
+    // Checks that an impl of a trait defines exactly its methods, leaving defaults optional
+    fn resolve_impl(&self, name: &str, kind: &str, methods: &[Statement]) -> Result<(), String> {
+        // `impl Iterator[int] for Counter` implements `Iterator`
+        let name = name.split('[').next().unwrap();
+
+        let declared = match self.traits.get(name) {
+            Some(declared) => declared,
+            None           => return Err(format!("impl of unknown trait {} for {}", name, kind)),
+        };
+
+        let mut defined = Vec::new();
+
+        for s in methods {
+            if let Statement::Expression(ref expr) = *s {
+                if let Expression::Definition(Some(ref method), _, ref params, ..) = **expr {
+                    let expected = match declared.iter().find(|m| m.name == *method) {
+                        Some(m) => m.params.len(),
+                        None    => return Err(format!("`{}` is not a method of {}", method, name)),
+                    };
+
+                    if params.len() != expected {
+                        return Err(format!(
+                            "`{}` of {} takes {} parameters but found {}", method, name, expected, params.len()
+                        ))
+                    }
+
+                    defined.push(method);
+                }
+            }
+        }
+
+        for m in declared {
+            if m.body.is_none() && !defined.contains(&&m.name) {
+                return Err(format!("missing method `{}` in impl of {} for {}", m.name, name, kind))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_jump(&self, keyword: &str, label: &Option<String>) -> Result<(), String> {
+        if self.loops.is_empty() {
+            return Err(format!("`{}` outside of loop", keyword))
+        }
+
+        if let Some(ref l) = *label {
+            if !self.loops.contains(&Some(l.clone())) {
+                return Err(format!("`{}` to undeclared label: {}", keyword, l))
+            }
+        }
+
+        Ok(())
+    }
+
+    // Whether `expression` names an imported module rather than a local binding shadowing it
+    fn is_import(&self, expression: &Expression) -> bool {
+        match *expression {
+            Expression::Identifier(ref id) => self.imports.contains_key(id) && self.lookup(id).is_none(),
+            _                              => false,
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match *statement {
+            Statement::If(ref condition, ref body) => {
+                try!(self.resolve_expression(condition));
+                self.resolve_statement(body)
+            },
+
+            Statement::IfElse(ref condition, ref body, ref else_body) => {
+                try!(self.resolve_expression(condition));
+                try!(self.resolve_statement(body));
+                self.resolve_statement(else_body)
+            },
+
+            Statement::Try(ref body, ref name, ref handler, _) => {
+                self.tries += 1;
+                let result = self.resolve_statement(body);
+                self.tries -= 1;
+
+                try!(result);
+
+                self.scopes.push(HashMap::new());
+
+                if let Some(ref name) = *name {
+                    self.declare(name, false);
+                }
+
+                let result = self.resolve_statement(handler);
+
+                self.scopes.pop();
+
+                result
+            },
+
+            // deferred code runs as the block exits, so it can't jump out of loops
+            // around it, nor return or yield from the function it's in
+            Statement::Defer(ref body) => {
+                if self.scopes.len() == 1 {
+                    return Err(String::from("`defer` outside of a block"))
+                }
+
+                let loops    = ::std::mem::take(&mut self.loops);
+                let deferred = ::std::mem::replace(&mut self.deferred, true);
+
+                let result = self.resolve_statement(body);
+
+                self.loops    = loops;
+                self.deferred = deferred;
+
+                result
+            },
+
+            Statement::While(ref label, ref condition, ref body) => {
+                try!(self.resolve_expression(condition));
+
+                self.loops.push(label.clone());
+                let result = self.resolve_statement(body);
+                self.loops.pop();
+
+                result
+            },
+
+            Statement::For(ref label, ref name, ref iterable, ref body) => {
+                try!(self.resolve_expression(iterable));
+
+                self.scopes.push(HashMap::new());
+                self.declare(name, false);
+
+                self.loops.push(label.clone());
+                let result = self.resolve_statement(body);
+                self.loops.pop();
+
+                self.scopes.pop();
+
+                result
+            },
+
+            Statement::Break(ref label)    => self.resolve_jump("break", label),
+            Statement::Continue(ref label) => self.resolve_jump("continue", label),
+
+            Statement::Block(ref stack) => {
+                self.scopes.push(HashMap::new());
+                let result = self.resolve(stack);
+                self.scopes.pop();
+
+                result
+            },
+
+            Statement::Expansion(ref name, call, defined, ref stack) => {
+                self.scopes.push(HashMap::new());
+                let result = self.resolve(stack);
+                self.scopes.pop();
+
+                result.map_err(|e| in_expansion(e, name, call, defined))
+            },
+
+            Statement::Public(ref statement) => self.resolve_statement(statement),
+
+            // the loader only follows imports it finds at the top of a module
+            Statement::Import(ref import, _) => match self.scopes.len() {
+                1 => Ok(()),
+                _ => Err(format!("import {} must be at the top of a module", import.join("."))),
+            },
+
+            Statement::Struct(..) |
+            Statement::Enum(..)   |
+            Statement::Macro(..)  => Ok(()),
+
+            Statement::Trait(_, _, ref methods) => {
+                for m in methods.iter() {
+                    if let Some(ref body) = m.body {
+                        try!(self.resolve_function(&m.params, body));
+                    }
+                }
+
+                Ok(())
+            },
+
+            // methods are reached through their receiver, so they aren't declared in scope
+            Statement::Impl(ref name, ref kind, ref methods) => {
+                if let Some(ref name) = *name {
+                    try!(self.resolve_impl(name, kind, methods));
+                }
+
+                for s in methods.iter() {
+                    if let Statement::Expression(ref expr) = *s {
+                        if let Expression::Definition(_, _, ref params, ref body, _, _) = **expr {
+                            try!(self.resolve_function(params, body));
+                        }
+                    }
+                }
+
+                Ok(())
+            },
+
+            Statement::Variable(ref name, mutable, ref expr) => {
+                try!(self.resolve_expression(expr));
+
+                match **expr {
+                    Expression::Definition(_, _, ref params, ..) |
+                    Expression::Lambda(ref params, ..) if !mutable => self.declare_function(name, params),
+
+                    _ => self.declare(name, mutable),
+                }
+
+                Ok(())
+            },
+
+            Statement::Variables(ref names, mutable, ref expr) => {
+                try!(self.resolve_expression(expr));
+
+                if let Expression::Tuple(ref values) = **expr {
+                    if values.len() != names.len() {
+                        return Err(format!("{} names bound to {} values", names.len(), values.len()))
+                    }
+                }
+
+                for name in names.iter() {
+                    self.declare(name, mutable);
+                }
+
+                Ok(())
+            },
+
+            Statement::Const(ref name, ref expr) => {
+                if self.scopes.len() > 1 {
+                    return Err(format!("const {} must be declared at the top of a module", name))
+                }
+
+                try!(self.resolve_expression(expr));
+
+                match **expr {
+                    Expression::Definition(..) => (),
+                    _                          => self.declare(name, false),
+                }
+
+                Ok(())
+            },
+
+            Statement::Assignment(ref name, ref expr) => {
+                try!(self.resolve_assignment(name));
+                self.resolve_expression(expr)
+            },
+
+            Statement::Expression(ref expr) => self.resolve_expression(expr),
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) -> Result<(), String> {
+        match *expression {
+            Expression::Call(ref callee, ref args, ref named, _) => {
+                try!(self.resolve_expression(callee));
+
+                for a in args.iter() {
+                    try!(self.resolve_expression(a));
+                }
+
+                for (_, a) in named.iter() {
+                    try!(self.resolve_expression(a));
+                }
+
+                match **callee {
+                    Expression::Identifier(ref name) => self.resolve_arity(name, args.len(), named),
+                    _                                => Ok(()),
+                }
+            },
+
+            Expression::Array(ref content) |
+            Expression::Tuple(ref content) |
+            Expression::Set(ref content)   => {
+                for e in content.iter() {
+                    try!(self.resolve_expression(e));
+                }
+
+                Ok(())
+            },
+
+            Expression::Map(ref entries) => {
+                for (k, v) in entries.iter() {
+                    try!(self.resolve_expression(k));
+                    try!(self.resolve_expression(v));
+                }
+
+                Ok(())
+            },
+
+            Expression::Dot(ref left, ref right) if self.is_import(left) => {
+                if let (Expression::Identifier(alias), Expression::Identifier(name)) = (&**left, &**right) {
+                    let (ref module, ref exports) = self.imports[alias];
+
+                    if !exports.contains(name) {
+                        return Err(format!("`{}` is not exported by {}", name, module))
+                    }
+                }
+
+                Ok(())
+            },
+
+            Expression::Assignment(ref target, ref expr)            |
+            Expression::CompoundAssignment(ref target, _, ref expr) => {
+                try!(self.resolve_target(target));
+                self.resolve_expression(expr)
+            },
+
+            Expression::Dot(ref left, ref right)          |
+            Expression::Index(ref left, ref right)        |
+            Expression::Range(ref left, ref right, _)     |
+            Expression::Operation(ref left, _, ref right, _) => {
+                try!(self.resolve_expression(left));
+                self.resolve_expression(right)
+            },
+
+            Expression::Typed(ref expr, _) => self.resolve_expression(expr),
+
+            Expression::Return(ref expr) => {
+                if self.deferred {
+                    return Err(String::from("`return` inside `defer`"))
+                }
+
+                match *expr {
+                    Some(ref expr) => self.resolve_expression(expr),
+                    None           => Ok(()),
+                }
+            },
+
+            // at the top of a module there is no caller to pass an error on to
+            Expression::Throw(ref expr, position) => {
+                if self.depth == 0 && self.tries == 0 {
+                    return Err(format!(
+                        "`throw` at line {}, column {} is outside of a function or `try`", position.0, position.1
+                    ))
+                }
+
+                self.resolve_expression(expr)
+            },
+
+            // a function that yields becomes a generator, so there has to be one
+            Expression::Yield(ref expr) => {
+                if self.depth == 0 {
+                    return Err(String::from("`yield` outside of a function"))
+                }
+
+                if self.deferred {
+                    return Err(String::from("`yield` inside `defer`"))
+                }
+
+                self.resolve_expression(expr)
+            },
+
+            Expression::Record(_, ref fields) => {
+                for (_, e) in fields.iter() {
+                    try!(self.resolve_expression(e));
+                }
+
+                Ok(())
+            },
+
+            Expression::Match(ref subject, ref arms) => {
+                try!(self.resolve_expression(subject));
+
+                for (pattern, guard, body) in arms.iter() {
+                    self.scopes.push(HashMap::new());
+                    self.declare_pattern(pattern);
+
+                    let result = match *guard {
+                        Some(ref g) => self.resolve_expression(g).and_then(|_| self.resolve_expression(body)),
+                        None        => self.resolve_expression(body),
+                    };
+
+                    self.scopes.pop();
+
+                    try!(result);
+                }
+
+                Ok(())
+            },
+
+            Expression::Definition(ref name, _, ref params, ref body, _, _) => {
+                if let Some(ref n) = *name {
+                    self.declare_function(n, params);
+                }
+
+                self.resolve_function(params, body)
+            },
+
+            Expression::Lambda(ref params, ref body, _, _) => {
+                self.resolve_function(params, ::std::slice::from_ref(&**body))
+            },
+
+            Expression::Integer(_)    |
+            Expression::BigInteger(_) |
+            Expression::Float(_)      |
+            Expression::Text(_)       |
+            Expression::Char(_)       |
+            Expression::Bool(_)       |
+            Expression::Identifier(_) => Ok(()),
+        }
+    }
+}
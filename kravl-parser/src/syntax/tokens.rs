@@ -3,11 +3,29 @@ pub enum TokenType {
     Integer,
     Float,
     Text,
+    Char,
     Identifier,
     Assign,
+    CompoundAssign,
+    Let,
+    Mut,
     Definition,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Macro,
+    Const,
+    Import,
+    As,
+    Pub,
     Lambda,
     Return,
+    Try,
+    Catch,
+    Throw,
+    Yield,
+    Defer,
     Arrow,
     Do,
     End,
@@ -22,10 +40,19 @@ pub enum TokenType {
     Colon,
     Comma,
     Period,
+    Range,
+    RangeInclusive,
+    Ellipsis,
     False,
     True,
     If,
     Else,
+    Match,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
     BinOp,
     Semicolon,
 }
@@ -42,6 +69,32 @@ pub enum BinOp {
     Gt,
     LtEqual,
     GtEqual,
+    Compose,
+    Pipe,
+}
+
+impl BinOp {
+    // Trait and method a user type implements to overload the operator,
+    // `~=` negating `eq` and the comparisons looking at the sign of `cmp`
+    pub fn overload(&self) -> Option<(&str, &str)> {
+        match *self {
+            BinOp::Plus     => Some(("Add", "add")),
+            BinOp::Minus    => Some(("Sub", "sub")),
+            BinOp::Mul      => Some(("Mul", "mul")),
+            BinOp::Div      => Some(("Div", "div")),
+
+            BinOp::Equal    |
+            BinOp::NotEqual => Some(("Eq", "eq")),
+
+            BinOp::Lt       |
+            BinOp::Gt       |
+            BinOp::LtEqual  |
+            BinOp::GtEqual  => Some(("Ord", "cmp")),
+
+            BinOp::Compose  |
+            BinOp::Pipe     => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -49,16 +102,24 @@ pub struct Token {
     pub token_type: TokenType,
     pub content:    String,
     pub row:        u32,
+    // columns the token starts at and ends at
+    pub start:      u32,
     pub col:        u32,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, content: String, row: u32, col: u32) -> Token {
+    pub fn new(token_type: TokenType, content: String, row: u32, start: u32, col: u32) -> Token {
         Token {
             token_type: token_type,
             content:    content,
             row:        row,
+            start:      start,
             col:        col,
         }
     }
+
+    // Row and column, counted from 1, the token starts at
+    pub fn position(&self) -> (u32, u32) {
+        (self.row, self.start + 1)
+    }
 }
\ No newline at end of file
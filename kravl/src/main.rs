@@ -1,9 +1,9 @@
 extern crate kravl_parser;
 
 use std::env;
-use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::path::Path;
 
 use kravl_parser::syntax;
 
@@ -13,26 +13,23 @@ fn main() {
 
     if args.len() > 1 {
 
-        let mut lexer = syntax::lexer::Lexer::new();
+        let path = Path::new(&args[1]);
 
-        let path = &args[1];
-        let mut source = match File::open(path) {
-            Ok(f)  => f,
-            Err(_) => panic!("failed to open: {}", path),
+        let search_path = match env::var("KRAVL_PATH") {
+            Ok(p)  => env::split_paths(&p).collect(),
+            Err(_) => Vec::new(),
         };
 
-        let mut buffer = String::new();
-        source.read_to_string(&mut buffer).unwrap();
+        let mut loader = syntax::module::Loader::new(search_path);
 
-        lexer.tokenize(buffer);
-        
-        let mut parser = syntax::ast::Parser::from(lexer);      
-
-        let stack = parser.parse_full();
+        if let Err(e) = loader.load_entry(path) {
+            println!("{}", e);
+            std::process::exit(1)
+        }
 
-        for n in stack {
-            for j in n {
-                println!("{:?}", j)
+        for module in loader.get_modules() {
+            for s in &module.statements {
+                println!("{:?}", s)
             }
         }
 